@@ -13,6 +13,7 @@ const Callscript_growing = require ('./Callscript_growing');
 const Callscript_pp_filter = require ('./Callscript_pp_filter');
 const Callscript_pp_gen2d = require ('./Callscript_pp_gen2d');
 const Callscript_pp_gen3d = require ('./Callscript_pp_gen3d');
+const Callscript_pp_grid_page = require ('./Callscript_pp_grid_page');
 
 app.post ('/Callscript_func', (req, res) => {Callscript_func(req,res);});
 app.post ('/Callscript_toolkit', (req, res) => {Callscript_toolkit(req,res);});
@@ -20,5 +21,6 @@ app.post ('/Callscript_growing', (req, res) => {Callscript_growing(req,res);});
 app.post ('/Callscript_pp_filter', (req, res) => {Callscript_pp_filter(req,res);});
 app.post ('/Callscript_pp_gen2d', (req, res) => {Callscript_pp_gen2d(req,res);});
 app.post ('/Callscript_pp_gen3d', (req, res) => {Callscript_pp_gen3d(req,res);});
+app.post ('/Callscript_pp_grid_page', (req, res) => {Callscript_pp_grid_page(req,res);});
 
 app.listen(port, () => {console.log (`listening on port ${port}`)});
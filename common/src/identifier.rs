@@ -0,0 +1,15 @@
+use rdkit_rust::inchi::prelude::*;
+use rdkit_rust::graphmol::romol::*;
+
+pub struct Identifiers {
+	pub inchi: String,
+	pub inchikey: String,
+}
+
+// Standard InChI and InChIKey, None when the InChI cannot be generated (e.g. unsupported elements)
+pub fn inchi_identifiers(mol: &impl ROMolImplRef) -> Option<Identifiers> {
+	let inchi = mol.to_inchi().ok()?;
+	let inchikey = inchi_to_inchikey(&inchi).ok()?;
+
+	Some(Identifiers { inchi, inchikey })
+}
@@ -3,6 +3,9 @@ use zip_next as zip;
 
 use rdkit_rust::PickleMolOptions;
 
+pub mod identifier;
+pub mod scaffold;
+
 pub static DEFAULT_MOL_PICKLE_OPTIONS: PickleMolOptions = PickleMolOptions {
 	mol_props: true,
 	atom_props: true,
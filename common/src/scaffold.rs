@@ -0,0 +1,74 @@
+use itertools::Itertools;
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::chemtransforms::prelude::*;
+use rdkit_rust::graphmol::ringinfo::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::prelude::*;
+
+pub struct Scaffolds {
+	pub scaffold: String,
+	pub generic: String,
+}
+
+// Bemis-Murcko scaffold and its generic (all atoms carbon, all bonds single) form.
+// Acyclic molecules lead to empty scaffolds.
+pub fn murcko_scaffolds(mol: &impl ROMolImplRef) -> Option<Scaffolds> {
+	let scaffold = mol.murcko_decompose().ok()?;
+	let generic = scaffold.make_scaffold_generic().ok()?;
+
+	Some(Scaffolds {
+		scaffold: scaffold.to_smiles().ok()?,
+		generic: generic.to_smiles().ok()?,
+	})
+}
+
+// Atom indices of each ring system (SSSR rings sharing at least one atom, i.e. fused or spiro)
+pub fn ring_systems(mol: &impl ROMolImplRef) -> Vec<Vec<u32>> {
+	let mut systems: Vec<Vec<u32>> = Vec::new();
+
+	for ring in mol.get_ring_info().atom_rings() {
+		let (fused, mut others): (Vec<_>, Vec<_>) = systems
+			.into_iter()
+			.partition(|system| ring.iter().any(|idx| system.contains(idx)));
+
+		others.push(fused
+			.into_iter()
+			.flatten()
+			.chain(ring)
+			.unique()
+			.collect());
+
+		systems = others;
+	}
+
+	systems
+}
+
+// Sorted SMILES of every ring system, duplicate ring systems are kept
+pub fn ring_system_smiles(mol: &impl ROMolImplRef) -> Vec<String> {
+	ring_systems(mol)
+		.iter()
+		.filter_map(|atoms| mol.to_smiles_fragment(atoms).ok())
+		.sorted()
+		.collect()
+}
+
+// Ring systems of `mol_ring_systems` that are not found in `ref_ring_systems` (multiset difference)
+pub fn new_ring_systems(ref_ring_systems: &[String], mol_ring_systems: &[String]) -> Vec<String> {
+	let mut ref_counts = ref_ring_systems
+		.iter()
+		.counts();
+
+	mol_ring_systems
+		.iter()
+		.filter(|smiles| match ref_counts.get_mut(smiles) {
+			Some(count) if *count > 0 => {
+				*count -= 1;
+				false
+			},
+			_ => true,
+		})
+		.cloned()
+		.collect()
+}
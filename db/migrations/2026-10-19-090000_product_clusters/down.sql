@@ -0,0 +1,4 @@
+ALTER TABLE experiment_product DROP COLUMN "cluster_centroid";
+ALTER TABLE experiment_product DROP COLUMN "cluster_idx";
+
+ALTER TABLE experiment DROP COLUMN "cluster_cutoff";
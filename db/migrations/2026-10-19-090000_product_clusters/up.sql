@@ -0,0 +1,5 @@
+-- Butina clustering cutoff (Tanimoto distance), NULL when clustering was not requested
+ALTER TABLE experiment ADD COLUMN "cluster_cutoff" real;
+
+ALTER TABLE experiment_product ADD COLUMN "cluster_idx" integer;
+ALTER TABLE experiment_product ADD COLUMN "cluster_centroid" boolean;
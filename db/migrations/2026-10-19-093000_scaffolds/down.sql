@@ -0,0 +1,8 @@
+DROP INDEX index__experiment_product__scaffold_smiles;
+
+ALTER TABLE experiment_product DROP COLUMN "new_ring_systems";
+ALTER TABLE experiment_product DROP COLUMN "generic_scaffold_smiles";
+ALTER TABLE experiment_product DROP COLUMN "scaffold_smiles";
+
+ALTER TABLE building_block DROP COLUMN "generic_scaffold_smiles";
+ALTER TABLE building_block DROP COLUMN "scaffold_smiles";
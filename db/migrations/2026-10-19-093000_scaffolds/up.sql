@@ -0,0 +1,10 @@
+-- Bemis-Murcko scaffolds, empty for acyclic molecules
+ALTER TABLE building_block ADD COLUMN "scaffold_smiles" varchar;
+ALTER TABLE building_block ADD COLUMN "generic_scaffold_smiles" varchar;
+
+ALTER TABLE experiment_product ADD COLUMN "scaffold_smiles" varchar;
+ALTER TABLE experiment_product ADD COLUMN "generic_scaffold_smiles" varchar;
+-- Ring systems of the product that are not present in the fragment, as a dot-separated SMILES
+ALTER TABLE experiment_product ADD COLUMN "new_ring_systems" varchar;
+
+CREATE INDEX index__experiment_product__scaffold_smiles ON experiment_product USING btree (id_experiment_frag_reactant, scaffold_smiles);
@@ -0,0 +1,7 @@
+ALTER TABLE experiment_postproc_filter DROP COLUMN "top_n";
+
+DROP INDEX index__experiment_product__score;
+
+ALTER TABLE experiment_product DROP COLUMN "score";
+
+ALTER TABLE experiment DROP COLUMN "score_profile";
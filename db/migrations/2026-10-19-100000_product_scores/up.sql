@@ -0,0 +1,9 @@
+-- Scoring profile (desirability functions and weights) used for the experiment, NULL when products are not scored
+ALTER TABLE experiment ADD COLUMN "score_profile" jsonb;
+
+ALTER TABLE experiment_product ADD COLUMN "score" real;
+
+CREATE INDEX index__experiment_product__score ON experiment_product USING btree (id_experiment_frag_reactant, score);
+
+-- Only keep the N best ranked products, NULL to keep all of them
+ALTER TABLE experiment_postproc_filter ADD COLUMN "top_n" integer;
@@ -0,0 +1,3 @@
+UPDATE reaction SET "reference" = NULL WHERE "reference" = 'doi:10.1021/ci200379p';
+
+ALTER TABLE experiment_product DROP COLUMN "route_smiles";
@@ -0,0 +1,4 @@
+-- Atom-mapped reaction SMILES (fragment.building_block>>product)
+ALTER TABLE experiment_product ADD COLUMN "route_smiles" varchar;
+
+UPDATE reaction SET "reference" = 'doi:10.1021/ci200379p' WHERE "name" LIKE 'Hartenfeller %' AND "reference" IS NULL;
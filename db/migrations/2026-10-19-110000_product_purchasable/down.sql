@@ -0,0 +1,6 @@
+DROP INDEX index__compound__smiles;
+
+ALTER TABLE experiment_postproc_filter DROP COLUMN "purchasable";
+
+ALTER TABLE experiment_product DROP COLUMN "purchasable_refids";
+ALTER TABLE experiment_product DROP COLUMN "purchasable";
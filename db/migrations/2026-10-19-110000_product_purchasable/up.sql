@@ -0,0 +1,8 @@
+-- Products sold as catalogue compounds, refids are listed as "provider:refid"
+ALTER TABLE experiment_product ADD COLUMN "purchasable" boolean NOT NULL DEFAULT false;
+ALTER TABLE experiment_product ADD COLUMN "purchasable_refids" varchar;
+
+-- NULL keeps both purchasable and non purchasable products
+ALTER TABLE experiment_postproc_filter ADD COLUMN "purchasable" boolean;
+
+CREATE INDEX index__compound__smiles ON compound USING btree (smiles);
@@ -0,0 +1,9 @@
+DROP INDEX index__experiment_product__inchikey;
+
+ALTER TABLE experiment_product DROP COLUMN "inchikey";
+ALTER TABLE experiment_product DROP COLUMN "inchi";
+
+DROP INDEX index__building_block__inchikey;
+
+ALTER TABLE building_block DROP COLUMN "inchikey";
+ALTER TABLE building_block DROP COLUMN "inchi";
@@ -0,0 +1,10 @@
+-- Standard InChI and InChIKey, NULL when they could not be generated
+ALTER TABLE building_block ADD COLUMN "inchi" varchar;
+ALTER TABLE building_block ADD COLUMN "inchikey" varchar;
+
+CREATE INDEX index__building_block__inchikey ON building_block USING btree (inchikey);
+
+ALTER TABLE experiment_product ADD COLUMN "inchi" varchar;
+ALTER TABLE experiment_product ADD COLUMN "inchikey" varchar;
+
+CREATE INDEX index__experiment_product__inchikey ON experiment_product USING btree (id_experiment_frag_reactant, inchikey);
@@ -0,0 +1,7 @@
+ALTER TABLE experiment_product DROP COLUMN "incompatible_groups";
+ALTER TABLE experiment_product DROP COLUMN "protecting_groups";
+
+ALTER TABLE building_block_reactant DROP COLUMN "excluded";
+ALTER TABLE building_block_reactant DROP COLUMN "incompatible_groups";
+
+DROP TABLE reaction_incompatible_group;
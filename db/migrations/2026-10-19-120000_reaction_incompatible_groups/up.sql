@@ -0,0 +1,25 @@
+-- Functional groups of a building block interfering with a reaction (e.g. a free amine on the acid of an amide coupling)
+-- Building blocks carrying an excluded group are discarded for the reaction, the other groups must be protected
+CREATE TABLE reaction_incompatible_group (
+	"id" bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	"id_reaction" bigint NOT NULL,
+	"reactant_idx" int,
+	"name" varchar NOT NULL,
+	"rdpickle" bytea NOT NULL,
+	"smarts" varchar NOT NULL,
+	"exclude" boolean NOT NULL
+);
+
+ALTER TABLE reaction_incompatible_group ADD CONSTRAINT fk__reaction_incompatible_group__reaction
+	FOREIGN KEY ("id_reaction")
+	REFERENCES reaction("id");
+
+CREATE INDEX index__reaction_incompatible_group__id_reaction ON reaction_incompatible_group USING btree (id_reaction);
+
+-- Names of the incompatible groups found, separated by ','
+ALTER TABLE building_block_reactant ADD COLUMN "incompatible_groups" varchar;
+ALTER TABLE building_block_reactant ADD COLUMN "excluded" boolean NOT NULL DEFAULT false;
+
+-- Number of groups of the building block to protect for the reaction
+ALTER TABLE experiment_product ADD COLUMN "protecting_groups" int NOT NULL DEFAULT 0;
+ALTER TABLE experiment_product ADD COLUMN "incompatible_groups" varchar;
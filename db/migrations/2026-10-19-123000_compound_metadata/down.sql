@@ -0,0 +1 @@
+ALTER TABLE compound DROP COLUMN "metadata";
@@ -0,0 +1,2 @@
+-- Extra vendor fields listed in the providers configuration
+ALTER TABLE compound ADD COLUMN "metadata" jsonb;
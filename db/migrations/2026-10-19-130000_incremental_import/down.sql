@@ -0,0 +1,5 @@
+DROP INDEX index__building_block_origin__id_compound__id_building_block;
+
+ALTER TABLE building_block DROP COLUMN "ts_reactants";
+
+ALTER TABLE compound DROP COLUMN "ts_seen";
@@ -0,0 +1,9 @@
+-- Start of the last import listing the compound, older ones were withdrawn by the vendor
+ALTER TABLE compound ADD COLUMN "ts_seen" timestamp;
+
+-- Reactant templates were matched against the building block, NULL while pending
+-- Previous imports always matched every building block
+ALTER TABLE building_block ADD COLUMN "ts_reactants" timestamp;
+UPDATE building_block SET "ts_reactants" = now();
+
+CREATE UNIQUE INDEX index__building_block_origin__id_compound__id_building_block ON building_block_origin USING btree (id_compound, id_building_block);
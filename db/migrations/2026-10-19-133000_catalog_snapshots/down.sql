@@ -0,0 +1,14 @@
+ALTER TABLE experiment DROP COLUMN "id_catalog_snapshot";
+
+DROP INDEX index__building_block_origin__id_compound__id_building_block;
+
+-- Past origins cannot be represented without snapshots
+DELETE FROM building_block_origin WHERE id_catalog_snapshot_to IS NOT NULL;
+
+CREATE UNIQUE INDEX index__building_block_origin__id_compound__id_building_block ON building_block_origin USING btree (id_compound, id_building_block);
+
+ALTER TABLE building_block_origin DROP COLUMN "id_catalog_snapshot_to";
+ALTER TABLE building_block_origin DROP COLUMN "id_catalog_snapshot_from";
+
+DROP TABLE catalog_snapshot_file;
+DROP TABLE catalog_snapshot;
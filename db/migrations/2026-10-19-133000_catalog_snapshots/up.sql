@@ -0,0 +1,55 @@
+-- Each catalog import, with the hashes of the vendor files it read
+CREATE TABLE catalog_snapshot (
+	"id" bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	"ts" timestamp NOT NULL
+);
+
+CREATE TABLE catalog_snapshot_file (
+	"id" bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	"id_catalog_snapshot" bigint NOT NULL,
+	"id_compound_provider" bigint NOT NULL,
+	"filename" varchar NOT NULL,
+	"sha256" varchar NOT NULL
+);
+
+ALTER TABLE catalog_snapshot_file ADD CONSTRAINT fk__catalog_snapshot_file__catalog_snapshot
+	FOREIGN KEY ("id_catalog_snapshot")
+	REFERENCES catalog_snapshot("id");
+ALTER TABLE catalog_snapshot_file ADD CONSTRAINT fk__catalog_snapshot_file__compound_provider
+	FOREIGN KEY ("id_compound_provider")
+	REFERENCES compound_provider("id");
+
+CREATE INDEX index__catalog_snapshot_file__id_catalog_snapshot ON catalog_snapshot_file USING btree (id_catalog_snapshot);
+
+-- Catalog imported before snapshots were recorded
+INSERT INTO catalog_snapshot ("ts") VALUES (now());
+
+-- An origin is valid from the snapshot it was imported in, up to (excluding) the snapshot which withdrew or changed the compound
+ALTER TABLE building_block_origin ADD COLUMN "id_catalog_snapshot_from" bigint;
+ALTER TABLE building_block_origin ADD COLUMN "id_catalog_snapshot_to" bigint;
+
+UPDATE building_block_origin SET "id_catalog_snapshot_from" = (SELECT min(id) FROM catalog_snapshot);
+UPDATE building_block_origin SET "id_catalog_snapshot_to" = "id_catalog_snapshot_from"
+	WHERE id_compound IN (SELECT id FROM compound WHERE NOT available);
+
+ALTER TABLE building_block_origin ALTER COLUMN "id_catalog_snapshot_from" SET NOT NULL;
+
+ALTER TABLE building_block_origin ADD CONSTRAINT fk__building_block_origin__catalog_snapshot_from
+	FOREIGN KEY ("id_catalog_snapshot_from")
+	REFERENCES catalog_snapshot("id");
+ALTER TABLE building_block_origin ADD CONSTRAINT fk__building_block_origin__catalog_snapshot_to
+	FOREIGN KEY ("id_catalog_snapshot_to")
+	REFERENCES catalog_snapshot("id");
+
+-- Only the current origins are unique, past ones are kept for the previous snapshots
+DROP INDEX index__building_block_origin__id_compound__id_building_block;
+CREATE UNIQUE INDEX index__building_block_origin__id_compound__id_building_block ON building_block_origin USING btree (id_compound, id_building_block) WHERE id_catalog_snapshot_to IS NULL;
+
+-- Catalog the experiment ran against
+ALTER TABLE experiment ADD COLUMN "id_catalog_snapshot" bigint;
+UPDATE experiment SET "id_catalog_snapshot" = (SELECT min(id) FROM catalog_snapshot);
+ALTER TABLE experiment ALTER COLUMN "id_catalog_snapshot" SET NOT NULL;
+
+ALTER TABLE experiment ADD CONSTRAINT fk__experiment__catalog_snapshot
+	FOREIGN KEY ("id_catalog_snapshot")
+	REFERENCES catalog_snapshot("id");
@@ -0,0 +1 @@
+ALTER TABLE catalog_snapshot DROP COLUMN "standardizer_profile";
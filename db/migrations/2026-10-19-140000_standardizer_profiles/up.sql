@@ -0,0 +1,2 @@
+-- Building block selection rules used by the import
+ALTER TABLE catalog_snapshot ADD COLUMN "standardizer_profile" jsonb;
@@ -0,0 +1,2 @@
+-- Accepted and rejected compounds per provider and reason
+ALTER TABLE catalog_snapshot ADD COLUMN "import_summary" jsonb;
@@ -0,0 +1,13 @@
+ALTER TABLE experiment_postproc_filter DROP COLUMN "max_lead_time_days";
+ALTER TABLE experiment_postproc_filter DROP COLUMN "max_price_per_mg";
+
+ALTER TABLE experiment_product DROP COLUMN "bb_lead_time_days";
+ALTER TABLE experiment_product DROP COLUMN "bb_price_per_mg";
+
+ALTER TABLE experiment DROP COLUMN "max_lead_time_days";
+ALTER TABLE experiment DROP COLUMN "max_price_per_mg";
+
+ALTER TABLE compound DROP COLUMN "lead_time_days";
+ALTER TABLE compound DROP COLUMN "in_stock";
+ALTER TABLE compound DROP COLUMN "pack_sizes";
+ALTER TABLE compound DROP COLUMN "price_per_mg";
@@ -0,0 +1,16 @@
+-- Commercial details of the vendor compounds, when the catalog provides them
+ALTER TABLE compound ADD COLUMN "price_per_mg" real;
+ALTER TABLE compound ADD COLUMN "pack_sizes" varchar;
+ALTER TABLE compound ADD COLUMN "in_stock" bool;
+ALTER TABLE compound ADD COLUMN "lead_time_days" int;
+
+-- Building blocks are only used when one of their compounds satisfies the limits
+ALTER TABLE experiment ADD COLUMN "max_price_per_mg" real;
+ALTER TABLE experiment ADD COLUMN "max_lead_time_days" int;
+
+-- Lowest known price and lead time among the building blocks the product can be made from, each at its cheapest and fastest compound
+ALTER TABLE experiment_product ADD COLUMN "bb_price_per_mg" real;
+ALTER TABLE experiment_product ADD COLUMN "bb_lead_time_days" int;
+
+ALTER TABLE experiment_postproc_filter ADD COLUMN "max_price_per_mg" real;
+ALTER TABLE experiment_postproc_filter ADD COLUMN "max_lead_time_days" int;
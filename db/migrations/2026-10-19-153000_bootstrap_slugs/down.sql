@@ -0,0 +1,8 @@
+DROP INDEX index__moiety__slug;
+DROP INDEX index__moiety_group__slug;
+
+ALTER TABLE moiety DROP COLUMN "slug";
+ALTER TABLE moiety_group DROP COLUMN "slug";
+
+DROP INDEX index__reaction_incompatible_group__id_reaction__reactant_idx__name;
+DROP INDEX index__reaction__slug;
@@ -0,0 +1,77 @@
+-- Each bootstrap used to insert the reactions and moieties again, the duplicates are merged into the first one
+
+CREATE TEMPORARY TABLE reaction_duplicate AS
+	SELECT r.id, k.id_keep
+	FROM reaction r
+	INNER JOIN (SELECT slug, min(id) AS id_keep FROM reaction GROUP BY slug) k ON k.slug = r.slug
+	WHERE r.id <> k.id_keep;
+
+UPDATE experiment_frag_reactant SET id_reaction = d.id_keep
+	FROM reaction_duplicate d WHERE experiment_frag_reactant.id_reaction = d.id;
+
+-- Matches of the duplicates are the same as those of the kept reaction
+UPDATE experiment_product_origin SET id_building_block_reactant = k.id
+	FROM building_block_reactant bbr
+	INNER JOIN reaction_duplicate d ON d.id = bbr.id_reaction
+	INNER JOIN building_block_reactant k ON k.id_reaction = d.id_keep
+		AND k.id_building_block = bbr.id_building_block
+		AND k.reactant_idx = bbr.reactant_idx
+	WHERE experiment_product_origin.id_building_block_reactant = bbr.id;
+
+DELETE FROM building_block_reactant bbr
+	USING reaction_duplicate d
+	WHERE bbr.id_reaction = d.id
+		AND NOT EXISTS (SELECT 1 FROM experiment_product_origin epo WHERE epo.id_building_block_reactant = bbr.id);
+
+UPDATE building_block_reactant SET id_reaction = d.id_keep
+	FROM reaction_duplicate d WHERE building_block_reactant.id_reaction = d.id;
+
+UPDATE reaction_incompatible_group SET id_reaction = d.id_keep
+	FROM reaction_duplicate d WHERE reaction_incompatible_group.id_reaction = d.id;
+
+DELETE FROM reaction_incompatible_group g
+	USING reaction_incompatible_group k
+	WHERE k.id_reaction = g.id_reaction
+		AND k.reactant_idx IS NOT DISTINCT FROM g.reactant_idx
+		AND k.name = g.name
+		AND k.id < g.id;
+
+DELETE FROM reaction WHERE id IN (SELECT id FROM reaction_duplicate);
+
+DROP TABLE reaction_duplicate;
+
+CREATE UNIQUE INDEX index__reaction__slug ON reaction USING btree (slug);
+-- A group applying to any reactant (NULL) is unique too, without requiring NULLS NOT DISTINCT (PostgreSQL 15)
+CREATE UNIQUE INDEX index__reaction_incompatible_group__id_reaction__reactant_idx__name ON reaction_incompatible_group USING btree (id_reaction, COALESCE(reactant_idx, -1), name);
+
+-- Moieties are identified by a slug of their name, as the reactions
+
+ALTER TABLE moiety_group ADD COLUMN "slug" varchar;
+ALTER TABLE moiety ADD COLUMN "slug" varchar;
+
+UPDATE moiety_group SET "slug" = trim(BOTH '_' FROM lower(regexp_replace(name, '[^A-Za-z0-9]+', '_', 'g')));
+UPDATE moiety SET "slug" = trim(BOTH '_' FROM lower(regexp_replace(name, '[^A-Za-z0-9]+', '_', 'g')));
+
+UPDATE moiety SET id_moiety_group = k.id_keep
+	FROM moiety_group g
+	INNER JOIN (SELECT slug, min(id) AS id_keep FROM moiety_group GROUP BY slug) k ON k.slug = g.slug
+	WHERE moiety.id_moiety_group = g.id;
+
+DELETE FROM moiety_group g
+	USING moiety_group k
+	WHERE k.slug = g.slug AND k.id < g.id;
+
+UPDATE experiment_frag SET id_moiety = k.id_keep
+	FROM moiety m
+	INNER JOIN (SELECT slug, min(id) AS id_keep FROM moiety GROUP BY slug) k ON k.slug = m.slug
+	WHERE experiment_frag.id_moiety = m.id;
+
+DELETE FROM moiety m
+	USING moiety k
+	WHERE k.slug = m.slug AND k.id < m.id;
+
+ALTER TABLE moiety_group ALTER COLUMN "slug" SET NOT NULL;
+ALTER TABLE moiety ALTER COLUMN "slug" SET NOT NULL;
+
+CREATE UNIQUE INDEX index__moiety_group__slug ON moiety_group USING btree (slug);
+CREATE UNIQUE INDEX index__moiety__slug ON moiety USING btree (slug);
@@ -0,0 +1,3 @@
+ALTER TABLE building_block_reactant DROP COLUMN "obsolete";
+
+ALTER TABLE reaction DROP COLUMN "ts_reactants";
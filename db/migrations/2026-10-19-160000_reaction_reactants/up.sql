@@ -0,0 +1,7 @@
+-- Every building block was matched against the reaction, NULL while pending
+-- Reactions already bootstrapped were matched along with the building blocks
+ALTER TABLE reaction ADD COLUMN "ts_reactants" timestamp;
+UPDATE reaction SET "ts_reactants" = now();
+
+-- Match made with a previous definition of the reaction, only kept for the products of the experiments using it
+ALTER TABLE building_block_reactant ADD COLUMN "obsolete" boolean NOT NULL DEFAULT false;
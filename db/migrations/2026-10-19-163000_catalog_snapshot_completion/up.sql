@@ -0,0 +1,6 @@
+-- End of the import, NULL while it runs or when it failed: experiments only resolve against completed snapshots
+ALTER TABLE catalog_snapshot ADD COLUMN "ts_completed" timestamp;
+
+-- Imports write their summary once done, the first snapshot holds the catalog imported before snapshots were recorded
+UPDATE catalog_snapshot SET "ts_completed" = "ts"
+	WHERE import_summary IS NOT NULL OR id = (SELECT min(id) FROM catalog_snapshot);
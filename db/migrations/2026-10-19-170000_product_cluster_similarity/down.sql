@@ -0,0 +1 @@
+ALTER TABLE experiment_product DROP COLUMN "cluster_similarity";
@@ -0,0 +1,2 @@
+-- Tanimoto similarity to the centroid of the cluster, picks a representative when the centroid itself was filtered out
+ALTER TABLE experiment_product ADD COLUMN "cluster_similarity" real;
@@ -1,11 +1,16 @@
 pub mod functions {
 	use diesel::expression::functions::sql_function;
+	use diesel::sql_types::Nullable;
 	use super::sql_types::*;
 
 	sql_function! {
 		#[aggregate]
 		fn string_agg<ST: StringAggregatable>(value: ST, delimiter: <ST as StringAggregatable>::StringAggDelimiter) -> <ST as StringAggregatable>::StringAggResult;
 	}
+
+	sql_function! {
+		fn coalesce<ST: NotNullSingleValue>(value: Nullable<ST>, default: ST) -> ST;
+	}
 }
 
 pub mod helper_types {
@@ -18,6 +23,10 @@ pub mod helper_types {
 pub mod sql_types {
 	use diesel::sql_types::{Bytea, Nullable, SingleValue, SqlType, Text, is_nullable};
 
+	pub trait NotNullSingleValue: SqlType<IsNull = is_nullable::NotNull> + SingleValue {}
+
+	impl<ST> NotNullSingleValue for ST where ST: SqlType<IsNull = is_nullable::NotNull> + SingleValue {}
+
 	pub trait StringAggregatable: SingleValue {
 		type StringAggDelimiter: SqlType + SingleValue;
 		type StringAggResult: SqlType + SingleValue;
@@ -62,6 +62,25 @@ pub fn run_migrations(db_pool: &DBPool) -> Result<(), Box<dyn Error + Send + Syn
 	Ok(())
 }
 
+/// Runs `f` in a transaction, which is rolled back instead of committed for a dry run.
+pub fn transaction_or_dry_run<T>(conn: &mut PgConnection, dry_run: bool, f: impl FnOnce(&mut PgConnection) -> QueryResult<T>) -> QueryResult<T> {
+	let mut res = None;
+
+	let outcome = conn.transaction(|conn| {
+		res = Some(f(conn)?);
+
+		match dry_run {
+			true => Err(diesel::result::Error::RollbackTransaction),
+			false => Ok(()),
+		}
+	});
+
+	match outcome {
+		Ok(()) | Err(diesel::result::Error::RollbackTransaction) => res.ok_or(diesel::result::Error::RollbackTransaction),
+		Err(err) => Err(err),
+	}
+}
+
 pub fn vacuum_full_analyze(db_pool: &DBPool) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
 	let mut conn = db_pool.get()?;
 
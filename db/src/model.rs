@@ -8,7 +8,7 @@ use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::custom::{RealrangeExpressionMethods, RealrangeType};
-use crate::expression::dsl::{StringAgg, string_agg};
+use crate::expression::dsl::{StringAgg, coalesce, string_agg};
 use crate::schema::*;
 
 pub type DB = diesel::pg::Pg;
@@ -18,18 +18,33 @@ allow_columns_to_appear_in_same_group_by_clause!(
 	building_block::id,
 	building_block::rdpickle,
 	building_block::smiles,
+	building_block::scaffold_smiles,
+	building_block::inchikey,
 	building_block_reactant::id,
 	building_block_reactant::id_building_block,
 	building_block_reactant::id_reaction,
 	building_block_reactant::reactant_idx,
+	building_block_reactant::incompatible_groups,
+	compound::id,
+	compound::refid,
+	compound::available,
+	compound::price_per_mg,
+	compound::pack_sizes,
+	compound::in_stock,
+	compound::lead_time_days,
+	compound_provider::id,
+	compound_provider::name,
 	experiment::id,
 	experiment::name,
 	experiment_product::id,
+	experiment_product::dup_count,
 	experiment_product::fullname,
 	experiment_product::name,
 	experiment_product::rdpickle,
 	experiment_product::smiles,
 	reaction::id,
+	reaction::name,
+	reaction::reference,
 	reaction::slug);
 
 #[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
@@ -40,6 +55,11 @@ pub struct BuildingBlock {
 	pub id: i64,
 	pub rdpickle: Vec<u8>,
 	pub smiles: String,
+	pub scaffold_smiles: Option<String>,
+	pub generic_scaffold_smiles: Option<String>,
+	pub inchi: Option<String>,
+	pub inchikey: Option<String>,
+	pub ts_reactants: Option<NaiveDateTime>,
 }
 
 #[derive(AsChangeset, FieldCount, Insertable, Debug, PartialEq)]
@@ -48,6 +68,10 @@ pub struct BuildingBlock {
 pub struct NewBuildingBlock<'s> {
 	pub rdpickle: &'s [u8],
 	pub smiles: &'s str,
+	pub scaffold_smiles: Option<&'s str>,
+	pub generic_scaffold_smiles: Option<&'s str>,
+	pub inchi: Option<&'s str>,
+	pub inchikey: Option<&'s str>,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -59,6 +83,9 @@ pub struct BuildingBlockOrigin {
 	pub id: i64,
 	pub id_building_block: i64,
 	pub id_compound: i64,
+	pub id_catalog_snapshot_from: i64,
+	/// First snapshot the origin is no longer part of, None while current
+	pub id_catalog_snapshot_to: Option<i64>,
 }
 
 #[derive(AsChangeset, FieldCount, Insertable, Debug, PartialEq)]
@@ -67,6 +94,7 @@ pub struct BuildingBlockOrigin {
 pub struct NewBuildingBlockOrigin {
 	pub id_building_block: i64,
 	pub id_compound: i64,
+	pub id_catalog_snapshot_from: i64,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
@@ -76,9 +104,51 @@ pub struct ExportableBuildingBlock {
 	pub id: i64,
 	pub rdpickle: Vec<u8>,
 	pub smiles: String,
+	pub scaffold_smiles: Option<String>,
+	pub inchikey: Option<String>,
 	#[diesel(select_expression_type = StringAgg<Concat<Concat<compound_provider::columns::name, &'static str>, compound::columns::refid>, &'static str>)]
 	#[diesel(select_expression = string_agg(compound_provider::columns::name.concat("-").concat(compound::columns::refid), ","))]
 	pub name: String,
+	#[diesel(select_expression_type = diesel::helper_types::min<compound::price_per_mg>)]
+	#[diesel(select_expression = diesel::dsl::min(compound::price_per_mg))]
+	pub price_per_mg: Option<f32>,
+	#[diesel(select_expression_type = diesel::helper_types::min<compound::lead_time_days>)]
+	#[diesel(select_expression = diesel::dsl::min(compound::lead_time_days))]
+	pub lead_time_days: Option<i32>,
+}
+
+// One vendor compound of a building block, with the number of products it leads to through a given reaction
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = building_block)]
+#[diesel(check_for_backend(DB))]
+pub struct PurchasableBuildingBlock {
+	pub id: i64,
+	pub smiles: String,
+	pub inchikey: Option<String>,
+	#[diesel(select_expression_type = compound_provider::name)]
+	#[diesel(select_expression = compound_provider::name)]
+	pub provider: String,
+	#[diesel(select_expression_type = compound::refid)]
+	#[diesel(select_expression = compound::refid)]
+	pub refid: String,
+	#[diesel(select_expression_type = compound::price_per_mg)]
+	#[diesel(select_expression = compound::price_per_mg)]
+	pub price_per_mg: Option<f32>,
+	#[diesel(select_expression_type = compound::pack_sizes)]
+	#[diesel(select_expression = compound::pack_sizes)]
+	pub pack_sizes: Option<String>,
+	#[diesel(select_expression_type = compound::in_stock)]
+	#[diesel(select_expression = compound::in_stock)]
+	pub in_stock: Option<bool>,
+	#[diesel(select_expression_type = compound::lead_time_days)]
+	#[diesel(select_expression = compound::lead_time_days)]
+	pub lead_time_days: Option<i32>,
+	#[diesel(select_expression_type = reaction::slug)]
+	#[diesel(select_expression = reaction::slug)]
+	pub reaction_slug: String,
+	#[diesel(select_expression_type = diesel::dsl::count<experiment_product::id>)]
+	#[diesel(select_expression = diesel::dsl::count(experiment_product::id))]
+	pub product_count: i64,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -91,6 +161,10 @@ pub struct BuildingBlockReactant {
 	pub id_building_block: i64,
 	pub id_reaction: i64,
 	pub reactant_idx: i32,
+	pub incompatible_groups: Option<String>,
+	pub excluded: bool,
+	/// Matched with a previous definition of the reaction
+	pub obsolete: bool,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
@@ -103,6 +177,7 @@ pub struct MergedBuildingBlockReactant {
 	pub id_building_block: i64,
 	pub id_reaction: i64,
 	pub reactant_idx: i32,
+	pub incompatible_groups: Option<String>,
 	#[diesel(select_expression_type = building_block::rdpickle)]
 	#[diesel(select_expression = building_block::rdpickle)]
 	pub rdpickle: Vec<u8>,
@@ -125,6 +200,51 @@ pub struct NewBuildingBlockReactant {
 	pub id_building_block: i64,
 	pub id_reaction: i64,
 	pub reactant_idx: i32,
+	pub incompatible_groups: Option<String>,
+	pub excluded: bool,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = catalog_snapshot)]
+#[diesel(check_for_backend(DB))]
+pub struct CatalogSnapshot {
+	pub id: i64,
+	pub ts: NaiveDateTime,
+	pub standardizer_profile: Option<serde_json::Value>,
+	pub import_summary: Option<serde_json::Value>,
+	/// End of the import, None while it runs or when it failed
+	pub ts_completed: Option<NaiveDateTime>,
+}
+
+#[derive(AsChangeset, Insertable, Debug, PartialEq)]
+#[diesel(table_name = catalog_snapshot)]
+#[diesel(check_for_backend(DB))]
+pub struct NewCatalogSnapshot<'s> {
+	pub ts: NaiveDateTime,
+	pub standardizer_profile: Option<&'s serde_json::Value>,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
+#[diesel(table_name = catalog_snapshot_file)]
+#[diesel(belongs_to(CatalogSnapshot, foreign_key = id_catalog_snapshot))]
+#[diesel(belongs_to(CompoundProvider, foreign_key = id_compound_provider))]
+#[diesel(check_for_backend(DB))]
+pub struct CatalogSnapshotFile {
+	pub id: i64,
+	pub id_catalog_snapshot: i64,
+	pub id_compound_provider: i64,
+	pub filename: String,
+	pub sha256: String,
+}
+
+#[derive(AsChangeset, Insertable, Debug, PartialEq)]
+#[diesel(table_name = catalog_snapshot_file)]
+#[diesel(check_for_backend(DB))]
+pub struct NewCatalogSnapshotFile<'s> {
+	pub id_catalog_snapshot: i64,
+	pub id_compound_provider: i64,
+	pub filename: &'s str,
+	pub sha256: &'s str,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -138,6 +258,12 @@ pub struct Compound {
 	pub sdf: Option<String>,
 	pub smiles: Option<String>,
 	pub available: bool,
+	pub metadata: Option<serde_json::Value>,
+	pub ts_seen: Option<NaiveDateTime>,
+	pub price_per_mg: Option<f32>,
+	pub pack_sizes: Option<String>,
+	pub in_stock: Option<bool>,
+	pub lead_time_days: Option<i32>,
 }
 
 #[derive(AsChangeset, FieldCount, Insertable, Debug, PartialEq)]
@@ -149,6 +275,12 @@ pub struct NewCompound<'s> {
 	pub sdf: Option<&'s str>,
 	pub smiles: Option<&'s str>,
 	pub available: bool,
+	pub metadata: Option<&'s serde_json::Value>,
+	pub ts_seen: Option<NaiveDateTime>,
+	pub price_per_mg: Option<f32>,
+	pub pack_sizes: Option<&'s str>,
+	pub in_stock: Option<bool>,
+	pub lead_time_days: Option<i32>,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
@@ -178,6 +310,11 @@ pub struct Experiment {
 	pub status: String,
 	pub ts_start: NaiveDateTime,
 	pub ts_end: Option<NaiveDateTime>,
+	pub cluster_cutoff: Option<f32>,
+	pub score_profile: Option<serde_json::Value>,
+	pub id_catalog_snapshot: i64,
+	pub max_price_per_mg: Option<f32>,
+	pub max_lead_time_days: Option<i32>,
 }
 
 #[derive(AsChangeset, Insertable, Debug, PartialEq)]
@@ -188,6 +325,11 @@ pub struct NewExperiment<'s> {
 	pub status: &'s str,
 	pub ts_start: NaiveDateTime,
 	pub ts_end: Option<NaiveDateTime>,
+	pub cluster_cutoff: Option<f32>,
+	pub score_profile: Option<&'s serde_json::Value>,
+	pub id_catalog_snapshot: i64,
+	pub max_price_per_mg: Option<f32>,
+	pub max_lead_time_days: Option<i32>,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -223,6 +365,10 @@ pub struct ExperimentPostprocFilter {
 	pub desc_clogp: RealrangeType,
 	pub desc_mw: RealrangeType,
 	pub desc_tpsa: RealrangeType,
+	pub top_n: Option<i32>,
+	pub purchasable: Option<bool>,
+	pub max_price_per_mg: Option<f32>,
+	pub max_lead_time_days: Option<i32>,
 }
 
 #[derive(AsChangeset, Insertable, Debug, PartialEq)]
@@ -237,6 +383,10 @@ pub struct NewExperimentPostprocFilter {
 	pub desc_clogp: RealrangeType,
 	pub desc_mw: RealrangeType,
 	pub desc_tpsa: RealrangeType,
+	pub top_n: Option<i32>,
+	pub purchasable: Option<bool>,
+	pub max_price_per_mg: Option<f32>,
+	pub max_lead_time_days: Option<i32>,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -338,6 +488,22 @@ pub struct ExperimentProduct {
 	pub desc_clogp: f32,
 	pub desc_mw: f32,
 	pub desc_tpsa: f32,
+	pub cluster_idx: Option<i32>,
+	pub cluster_centroid: Option<bool>,
+	pub scaffold_smiles: Option<String>,
+	pub generic_scaffold_smiles: Option<String>,
+	pub new_ring_systems: Option<String>,
+	pub score: Option<f32>,
+	pub route_smiles: Option<String>,
+	pub purchasable: bool,
+	pub purchasable_refids: Option<String>,
+	pub inchi: Option<String>,
+	pub inchikey: Option<String>,
+	pub protecting_groups: i32,
+	pub incompatible_groups: Option<String>,
+	pub bb_price_per_mg: Option<f32>,
+	pub bb_lead_time_days: Option<i32>,
+	pub cluster_similarity: Option<f32>,
 }
 
 #[derive(AsChangeset, FieldCount, Insertable, Debug, PartialEq)]
@@ -356,6 +522,15 @@ pub struct NewExperimentProduct<'s> {
 	pub desc_clogp: f32,
 	pub desc_mw: f32,
 	pub desc_tpsa: f32,
+	pub scaffold_smiles: Option<&'s str>,
+	pub generic_scaffold_smiles: Option<&'s str>,
+	pub new_ring_systems: Option<&'s str>,
+	pub score: Option<f32>,
+	pub route_smiles: Option<&'s str>,
+	pub inchi: Option<&'s str>,
+	pub inchikey: Option<&'s str>,
+	pub protecting_groups: i32,
+	pub incompatible_groups: Option<&'s str>,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
@@ -367,11 +542,63 @@ pub struct ExportableExperimentProduct {
 	pub smiles: String,
 	pub name: String,
 	pub fullname: String,
+	pub desc_fsp3: f32,
+	pub desc_hba: i32,
+	pub desc_hbd: i32,
+	pub desc_clogp: f32,
+	pub desc_mw: f32,
+	pub desc_tpsa: f32,
+	pub cluster_idx: Option<i32>,
+	pub cluster_centroid: Option<bool>,
+	pub scaffold_smiles: Option<String>,
+	pub generic_scaffold_smiles: Option<String>,
+	pub new_ring_systems: Option<String>,
+	pub score: Option<f32>,
+	pub route_smiles: Option<String>,
+	pub purchasable: bool,
+	pub purchasable_refids: Option<String>,
+	pub inchi: Option<String>,
+	pub inchikey: Option<String>,
+	pub protecting_groups: i32,
+	pub incompatible_groups: Option<String>,
+	pub bb_price_per_mg: Option<f32>,
+	pub bb_lead_time_days: Option<i32>,
+	pub cluster_similarity: Option<f32>,
 	#[diesel(select_expression_type = experiment_frag_reactant::id_reaction)]
 	#[diesel(select_expression = experiment_frag_reactant::id_reaction)]
 	pub id_reaction: i64,
 }
 
+// Reaction, building blocks and vendor compounds a product originates from
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = experiment_product)]
+#[diesel(check_for_backend(DB))]
+pub struct ExperimentProductProvenance {
+	pub id: i64,
+	pub dup_count: i32,
+	#[diesel(select_expression_type = reaction::id)]
+	#[diesel(select_expression = reaction::id)]
+	pub id_reaction: i64,
+	#[diesel(select_expression_type = reaction::name)]
+	#[diesel(select_expression = reaction::name)]
+	pub reaction_name: String,
+	#[diesel(select_expression_type = reaction::slug)]
+	#[diesel(select_expression = reaction::slug)]
+	pub reaction_slug: String,
+	#[diesel(select_expression_type = reaction::reference)]
+	#[diesel(select_expression = reaction::reference)]
+	pub reaction_reference: Option<String>,
+	#[diesel(select_expression_type = building_block::smiles)]
+	#[diesel(select_expression = building_block::smiles)]
+	pub bb_smiles: String,
+	#[diesel(select_expression_type = StringAgg<compound::columns::refid, &'static str>)]
+	#[diesel(select_expression = string_agg(compound::columns::refid, ","))]
+	pub refids: String,
+	#[diesel(select_expression_type = StringAgg<compound_provider::columns::name, &'static str>)]
+	#[diesel(select_expression = string_agg(compound_provider::columns::name, ","))]
+	pub providers: String,
+}
+
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
 #[diesel(table_name = experiment_product_origin)]
 #[diesel(belongs_to(BuildingBlockReactant, foreign_key = id_building_block_reactant))]
@@ -420,6 +647,7 @@ pub struct Moiety {
 	pub id: i64,
 	pub id_moiety_group: i64,
 	pub name: String,
+	pub slug: String,
 	pub rdpickle : Vec<u8>,
 	pub smarts: String,
 	pub priority: i32,
@@ -431,6 +659,7 @@ pub struct Moiety {
 pub struct NewMoiety<'s> {
 	pub id_moiety_group: i64,
 	pub name: &'s str,
+	pub slug: &'s str,
 	pub rdpickle : &'s [u8],
 	pub smarts: &'s str,
 	pub priority: i32,
@@ -442,6 +671,7 @@ pub struct NewMoiety<'s> {
 pub struct MoietyGroup {
 	pub id: i64,
 	pub name: String,
+	pub slug: String,
 }
 
 #[derive(AsChangeset, Insertable, Debug, PartialEq)]
@@ -449,6 +679,7 @@ pub struct MoietyGroup {
 #[diesel(check_for_backend(DB))]
 pub struct NewMoietyGroup<'s> {
 	pub name: &'s str,
+	pub slug: &'s str,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -500,6 +731,8 @@ pub	struct Reaction {
 	pub smarts: String,
 	pub multistep: bool,
 	pub reference: Option<String>,
+	/// Every building block was matched against the reaction, None while pending
+	pub ts_reactants: Option<NaiveDateTime>,
 }
 
 #[derive(AsChangeset, Insertable, Debug, PartialEq)]
@@ -514,6 +747,34 @@ pub	struct NewReaction<'s> {
 	pub reference: Option<&'s str>,
 }
 
+#[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
+#[diesel(table_name = reaction_incompatible_group)]
+#[diesel(belongs_to(Reaction, foreign_key = id_reaction))]
+#[diesel(check_for_backend(DB))]
+pub struct ReactionIncompatibleGroup {
+	pub id: i64,
+	pub id_reaction: i64,
+	/// Reactant template the group applies to, any reactant when NULL
+	pub reactant_idx: Option<i32>,
+	pub name: String,
+	pub rdpickle: Vec<u8>,
+	pub smarts: String,
+	/// Discard the building block instead of requiring a protecting group
+	pub exclude: bool,
+}
+
+#[derive(AsChangeset, Insertable, Debug, PartialEq)]
+#[diesel(table_name = reaction_incompatible_group)]
+#[diesel(check_for_backend(DB))]
+pub struct NewReactionIncompatibleGroup<'s> {
+	pub id_reaction: i64,
+	pub reactant_idx: Option<i32>,
+	pub name: &'s str,
+	pub rdpickle: &'s [u8],
+	pub smarts: &'s str,
+	pub exclude: bool,
+}
+
 fn boxed_bool<T>(val: bool) -> Box<dyn BoxableExpression<T, DB, SqlType = Bool>> {
 	Box::new(AsExpression::<Bool>::as_expression(val))
 }
@@ -535,6 +796,13 @@ pub fn count_building_blocks(conn: &mut DBConnection) -> QueryResult<i64> {
 		.get_result(conn)
 }
 
+pub fn count_building_blocks_pending_reactants(conn: &mut DBConnection) -> QueryResult<i64> {
+	building_block::table
+		.filter(building_block::ts_reactants.is_null())
+		.count()
+		.get_result(conn)
+}
+
 pub fn count_building_blocks_with_experiment_providers(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<i64> {
 	experiment::table
 		.inner_join(experiment_selected_provider::table
@@ -543,6 +811,8 @@ pub fn count_building_blocks_with_experiment_providers(conn: &mut DBConnection,
 		.inner_join(building_block_origin::table
 		.inner_join(building_block::table)))))
 		.filter(experiment::id.eq(exp.id))
+		.filter(predicate_origin_in_experiment_snapshot())
+		.filter(predicate_compound_within_experiment_limits())
 		.select(diesel::dsl::count_distinct(building_block::id))
 		.get_result(conn)
 }
@@ -566,8 +836,13 @@ pub fn get_or_create_building_blocks(conn: &mut DBConnection, elems: &[NewBuildi
 		.values(elems)
 		.on_conflict(building_block::smiles)
 		.do_update()
-		// Dummy update
-		.set(building_block::smiles.eq(diesel::upsert::excluded(building_block::smiles)))
+		// Identifiers and scaffolds only depend on the SMILES, this also fills them for building blocks imported before they were computed
+		.set((
+			building_block::scaffold_smiles.eq(diesel::upsert::excluded(building_block::scaffold_smiles)),
+			building_block::generic_scaffold_smiles.eq(diesel::upsert::excluded(building_block::generic_scaffold_smiles)),
+			building_block::inchi.eq(diesel::upsert::excluded(building_block::inchi)),
+			building_block::inchikey.eq(diesel::upsert::excluded(building_block::inchikey)),
+		))
 		.get_results(conn)
 }
 
@@ -596,6 +871,23 @@ pub fn create_building_block_origins(conn: &mut DBConnection, elems: &[NewBuildi
 		.get_results(conn)
 }
 
+// Current origins of the compounds
+pub fn get_building_block_origins_with_compounds(conn: &mut DBConnection, ids: &[i64]) -> QueryResult<Vec<BuildingBlockOrigin>> {
+	building_block_origin::table
+		.filter(building_block_origin::id_compound.eq_any(ids))
+		.filter(building_block_origin::id_catalog_snapshot_to.is_null())
+		.load(conn)
+}
+
+// Origins are kept for the previous snapshots, they are only closed
+pub fn close_building_block_origins(conn: &mut DBConnection, ids: &[i64], snapshot: &CatalogSnapshot) -> QueryResult<usize> {
+	diesel::update(building_block_origin::table)
+		.filter(building_block_origin::id.eq_any(ids))
+		.filter(building_block_origin::id_catalog_snapshot_to.is_null())
+		.set(building_block_origin::id_catalog_snapshot_to.eq(snapshot.id))
+		.execute(conn)
+}
+
 pub fn get_building_block_origin(conn: &mut DBConnection, id: i64) -> QueryResult<BuildingBlockOrigin> {
 	building_block_origin::table.find(id)
 		.first(conn)
@@ -666,11 +958,86 @@ pub fn create_compounds_return(conn: &mut DBConnection, elem: &[NewCompound]) ->
 		.get_results(conn)
 }
 
+// Compounds already imported are made available again, with the record, metadata and offer of the new import
+pub fn upsert_compounds_return(conn: &mut DBConnection, elems: &[NewCompound]) -> QueryResult<Vec<Compound>> {
+	diesel::insert_into(compound::table)
+		.values(elems)
+		.on_conflict((compound::id_compound_provider, compound::refid))
+		.do_update()
+		.set((
+			compound::sdf.eq(diesel::upsert::excluded(compound::sdf)),
+			compound::smiles.eq(diesel::upsert::excluded(compound::smiles)),
+			compound::available.eq(diesel::upsert::excluded(compound::available)),
+			compound::metadata.eq(diesel::upsert::excluded(compound::metadata)),
+			compound::ts_seen.eq(diesel::upsert::excluded(compound::ts_seen)),
+			compound::price_per_mg.eq(diesel::upsert::excluded(compound::price_per_mg)),
+			compound::pack_sizes.eq(diesel::upsert::excluded(compound::pack_sizes)),
+			compound::in_stock.eq(diesel::upsert::excluded(compound::in_stock)),
+			compound::lead_time_days.eq(diesel::upsert::excluded(compound::lead_time_days)),
+		))
+		.get_results(conn)
+}
+
+/// Marks the compounds of the providers missing from the import of `snapshot` as unavailable, and closes their origins.
+pub fn withdraw_compounds_unseen_since(conn: &mut DBConnection, provider_ids: &[i64], snapshot: &CatalogSnapshot) -> QueryResult<usize> {
+	conn.transaction(|conn| {
+		let ids: Vec<i64> = diesel::update(compound::table)
+			.filter(compound::id_compound_provider.eq_any(provider_ids))
+			.filter(compound::available.eq(true))
+			.filter(compound::ts_seen.is_null().or(compound::ts_seen.lt(snapshot.ts)))
+			.set(compound::available.eq(false))
+			.returning(compound::id)
+			.get_results(conn)?;
+
+		diesel::update(building_block_origin::table)
+			.filter(building_block_origin::id_compound.eq_any(&ids))
+			.filter(building_block_origin::id_catalog_snapshot_to.is_null())
+			.set(building_block_origin::id_catalog_snapshot_to.eq(snapshot.id))
+			.execute(conn)?;
+
+		Ok(ids.len())
+	})
+}
+
+/// Withdraws every compound, so that the following import starts from an empty catalog.
+pub fn withdraw_all_compounds(conn: &mut DBConnection, snapshot: &CatalogSnapshot) -> QueryResult<usize> {
+	conn.transaction(|conn| {
+		diesel::update(building_block_origin::table)
+			.filter(building_block_origin::id_catalog_snapshot_to.is_null())
+			.set(building_block_origin::id_catalog_snapshot_to.eq(snapshot.id))
+			.execute(conn)?;
+
+		diesel::update(compound::table)
+			.filter(compound::available.eq(true))
+			.set(compound::available.eq(false))
+			.execute(conn)
+	})
+}
+
 pub fn get_compound(conn: &mut DBConnection, id: i64) -> QueryResult<Compound> {
 	compound::table.find(id)
 		.first(conn)
 }
 
+pub fn get_compound_by_provider_and_refid(conn: &mut DBConnection, provider_name: &str, refid: &str) -> QueryResult<Compound> {
+	compound::table
+		.inner_join(compound_provider::table)
+		.filter(compound_provider::name.eq(provider_name))
+		.filter(compound::refid.eq(refid))
+		.select(Compound::as_select())
+		.first(conn)
+}
+
+/// Building blocks a compound led to across the catalog snapshots, oldest first
+pub fn get_building_blocks_with_compound(conn: &mut DBConnection, compound: &Compound) -> QueryResult<Vec<(BuildingBlockOrigin, BuildingBlock)>> {
+	building_block_origin::table
+		.inner_join(building_block::table)
+		.filter(building_block_origin::id_compound.eq(compound.id))
+		.order(building_block_origin::id)
+		.select((BuildingBlockOrigin::as_select(), BuildingBlock::as_select()))
+		.load(conn)
+}
+
 pub fn update_compound(conn: &mut DBConnection, id: i64, elem: &NewCompound) -> QueryResult<Compound> {
 	diesel::update(compound::table)
 		.filter(compound::id.eq(id))
@@ -695,6 +1062,51 @@ pub fn delete_all_compounds_with_provider(conn: &mut DBConnection, provider: &Co
 		.execute(conn)
 }
 
+pub fn create_catalog_snapshot(conn: &mut DBConnection, elem: &NewCatalogSnapshot) -> QueryResult<CatalogSnapshot> {
+	diesel::insert_into(catalog_snapshot::table)
+		.values(elem)
+		.get_result(conn)
+}
+
+pub fn get_catalog_snapshot(conn: &mut DBConnection, id: i64) -> QueryResult<CatalogSnapshot> {
+	catalog_snapshot::table
+		.find(id)
+		.first(conn)
+}
+
+pub fn update_catalog_snapshot_import_summary(conn: &mut DBConnection, id: i64, import_summary: &serde_json::Value) -> QueryResult<CatalogSnapshot> {
+	diesel::update(catalog_snapshot::table.find(id))
+		.set(catalog_snapshot::import_summary.eq(import_summary))
+		.get_result(conn)
+}
+
+pub fn update_catalog_snapshot_ts_completed(conn: &mut DBConnection, id: i64, ts: NaiveDateTime) -> QueryResult<CatalogSnapshot> {
+	diesel::update(catalog_snapshot::table.find(id))
+		.set(catalog_snapshot::ts_completed.eq(ts))
+		.get_result(conn)
+}
+
+/// Last snapshot whose import completed, the catalog of a running or failed import is partial.
+pub fn get_last_catalog_snapshot(conn: &mut DBConnection) -> QueryResult<CatalogSnapshot> {
+	catalog_snapshot::table
+		.filter(catalog_snapshot::ts_completed.is_not_null())
+		.order(catalog_snapshot::id.desc())
+		.first(conn)
+}
+
+pub fn create_catalog_snapshot_files(conn: &mut DBConnection, elems: &[NewCatalogSnapshotFile]) -> QueryResult<Vec<CatalogSnapshotFile>> {
+	diesel::insert_into(catalog_snapshot_file::table)
+		.values(elems)
+		.get_results(conn)
+}
+
+pub fn get_catalog_snapshot_files_with_snapshot(conn: &mut DBConnection, snapshot: &CatalogSnapshot) -> QueryResult<Vec<CatalogSnapshotFile>> {
+	catalog_snapshot_file::table
+		.filter(catalog_snapshot_file::id_catalog_snapshot.eq(snapshot.id))
+		.order(catalog_snapshot_file::id)
+		.load(conn)
+}
+
 pub fn create_compound_provider(conn: &mut DBConnection, elem: &NewCompoundProvider) -> QueryResult<CompoundProvider> {
 	diesel::insert_into(compound_provider::table)
 		.values(elem)
@@ -711,12 +1123,31 @@ pub fn get_compound_provider_by_name(conn: &mut DBConnection, name: &str) -> Que
 		.first(conn)
 }
 
+/// Number of available and withdrawn compounds of each provider
+pub fn count_compounds_by_provider(conn: &mut DBConnection) -> QueryResult<Vec<(String, bool, i64)>> {
+	compound_provider::table
+		.inner_join(compound::table)
+		.group_by((compound_provider::id, compound::available))
+		.select((compound_provider::name, compound::available, diesel::dsl::count_star()))
+		.order((compound_provider::name, compound::available.desc()))
+		.load(conn)
+}
+
 pub fn get_compound_providers_by_name<'a>(conn: &'a mut DBConnection, name: &'a [&str]) -> QueryResult<impl Iterator<Item = QueryResult<CompoundProvider>> + 'a> {
 	compound_provider::table.filter(compound_provider::name.eq_any(name))
 		.select(CompoundProvider::as_select())
 		.load_iter::<_, PgRowByRowLoadingMode>(conn)
 }
 
+pub fn get_compound_providers_with_experiment(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<Vec<CompoundProvider>> {
+	compound_provider::table
+		.inner_join(experiment_selected_provider::table)
+		.filter(experiment_selected_provider::id_experiment.eq(exp.id))
+		.order(compound_provider::name)
+		.select(CompoundProvider::as_select())
+		.load(conn)
+}
+
 pub fn update_compound_provider(conn: &mut DBConnection, id: i64, elem: &NewCompoundProvider) -> QueryResult<CompoundProvider> {
 	diesel::update(compound_provider::table)
 		.filter(compound_provider::id.eq(id))
@@ -807,6 +1238,13 @@ pub fn get_last_experiment_postproc_filter_with_experiment(conn: &mut DBConnecti
 		.first(conn)
 }
 
+pub fn get_experiment_postproc_filters_with_experiment(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<Vec<ExperimentPostprocFilter>> {
+	experiment_postproc_filter::table
+		.order_by(experiment_postproc_filter::ts)
+		.filter(experiment_postproc_filter::id_experiment.eq(exp.id))
+		.load(conn)
+}
+
 pub fn update_experiment_postproc_filter(conn: &mut DBConnection, id: i64, elem: &NewExperimentPostprocFilter) -> QueryResult<ExperimentPostprocFilter> {
 	diesel::update(experiment_postproc_filter::table)
 		.filter(experiment_postproc_filter::id.eq(id))
@@ -855,6 +1293,13 @@ pub fn get_experiment_frag(conn: &mut DBConnection, id: i64) -> QueryResult<Expe
 		.first(conn)
 }
 
+pub fn get_experiment_frags_with_experiment(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<Vec<ExperimentFrag>> {
+	experiment_frag::table
+		.filter(experiment_frag::id_experiment.eq(exp.id))
+		.order(experiment_frag::idx)
+		.load(conn)
+}
+
 pub fn update_experiment_frag(conn: &mut DBConnection, id: i64, elem: &NewExperimentFrag) -> QueryResult<ExperimentFrag> {
 	diesel::update(experiment_frag::table)
 		.filter(experiment_frag::id.eq(id))
@@ -922,6 +1367,179 @@ pub fn delete_experiment_product(conn: &mut DBConnection, id: i64) -> QueryResul
 		.execute(conn)
 }
 
+// (id, cluster_idx, cluster_centroid, cluster_similarity)
+pub fn update_experiment_product_clusters(conn: &mut DBConnection, elems: &[(i64, i32, bool, f32)]) -> QueryResult<()> {
+	if elems.is_empty() {
+		return Ok(());
+	}
+
+	// A single statement for the whole chunk
+	let values = (0..elems.len())
+		.map(|idx| format!("(${}::bigint, ${}::int, ${}::boolean, ${}::real)", 4 * idx + 1, 4 * idx + 2, 4 * idx + 3, 4 * idx + 4))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	let query = diesel::sql_query(format!("UPDATE experiment_product \
+			SET cluster_idx = v.cluster_idx, cluster_centroid = v.cluster_centroid, cluster_similarity = v.cluster_similarity \
+			FROM (VALUES {values}) AS v (id, cluster_idx, cluster_centroid, cluster_similarity) \
+			WHERE experiment_product.id = v.id"))
+		.into_boxed::<DB>();
+
+	elems
+		.iter()
+		.fold(query, |query, (id, cluster_idx, cluster_centroid, cluster_similarity)| query
+			.bind::<diesel::sql_types::BigInt, _>(*id)
+			.bind::<diesel::sql_types::Integer, _>(*cluster_idx)
+			.bind::<Bool, _>(*cluster_centroid)
+			.bind::<diesel::sql_types::Float, _>(*cluster_similarity))
+		.execute(conn)
+		.map(|_| ())
+}
+
+// (id, additional duplicates)
+pub fn update_experiment_product_dup_counts(conn: &mut DBConnection, elems: &[(i64, i32)]) -> QueryResult<()> {
+	if elems.is_empty() {
+		return Ok(());
+	}
+
+	// A single statement for the whole chunk, an id listed several times gets the sum of its duplicates
+	let values = (0..elems.len())
+		.map(|idx| format!("(${}::bigint, ${}::int)", 2 * idx + 1, 2 * idx + 2))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	let query = diesel::sql_query(format!("UPDATE experiment_product \
+			SET dup_count = experiment_product.dup_count + v.dup_count \
+			FROM (SELECT id, sum(dup_count) AS dup_count FROM (VALUES {values}) AS e (id, dup_count) GROUP BY id) AS v \
+			WHERE experiment_product.id = v.id"))
+		.into_boxed::<DB>();
+
+	elems
+		.iter()
+		.fold(query, |query, (id, dup_count)| query
+			.bind::<diesel::sql_types::BigInt, _>(*id)
+			.bind::<diesel::sql_types::Integer, _>(*dup_count))
+		.execute(conn)
+		.map(|_| ())
+}
+
+// (id, refids)
+pub fn update_experiment_product_purchasables(conn: &mut DBConnection, elems: &[(i64, String)]) -> QueryResult<()> {
+	if elems.is_empty() {
+		return Ok(());
+	}
+
+	// A single statement for the whole chunk
+	let values = (0..elems.len())
+		.map(|idx| format!("(${}::bigint, ${}::varchar)", 2 * idx + 1, 2 * idx + 2))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	let query = diesel::sql_query(format!("UPDATE experiment_product \
+			SET purchasable = true, purchasable_refids = v.refids \
+			FROM (VALUES {values}) AS v (id, refids) \
+			WHERE experiment_product.id = v.id"))
+		.into_boxed::<DB>();
+
+	elems
+		.iter()
+		.fold(query, |query, (id, refids)| query
+			.bind::<diesel::sql_types::BigInt, _>(*id)
+			.bind::<diesel::sql_types::Varchar, _>(refids))
+		.execute(conn)
+		.map(|_| ())
+}
+
+/// (product id, building block reactant id, lowest price per mg, shortest lead time)
+pub type ProductBuildingBlockOffer = (i64, i64, Option<f32>, Option<i32>);
+
+/// Offers for the building blocks of the products, over the compounds of the selected providers within the experiment snapshot and limits.
+pub fn get_experiment_product_building_block_offers(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<Vec<ProductBuildingBlockOffer>> {
+	experiment::table
+		.inner_join(experiment_selected_provider::table)
+		.inner_join(experiment_frag::table
+		.inner_join(experiment_frag_reactant::table
+		.inner_join(experiment_product::table
+		.inner_join(experiment_product_origin::table
+		.inner_join(building_block_reactant::table
+		.inner_join(building_block::table
+		.inner_join(building_block_origin::table
+		.inner_join(compound::table))))))))
+		.filter(experiment_selected_provider::id_compound_provider.eq(compound::id_compound_provider))
+		.filter(experiment::id.eq(exp.id))
+		.filter(predicate_origin_in_experiment_snapshot())
+		.filter(predicate_compound_within_experiment_limits())
+		.group_by((experiment_product::id, building_block_reactant::id))
+		.select((
+			experiment_product::id,
+			building_block_reactant::id,
+			diesel::dsl::min(compound::price_per_mg),
+			diesel::dsl::min(compound::lead_time_days),
+		))
+		.load(conn)
+}
+
+// (id, bb_price_per_mg, bb_lead_time_days)
+pub fn update_experiment_product_offers(conn: &mut DBConnection, elems: &[(i64, Option<f32>, Option<i32>)]) -> QueryResult<()> {
+	if elems.is_empty() {
+		return Ok(());
+	}
+
+	// A single statement for the whole chunk
+	let values = (0..elems.len())
+		.map(|idx| format!("(${}::bigint, ${}::real, ${}::int)", 3 * idx + 1, 3 * idx + 2, 3 * idx + 3))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	let query = diesel::sql_query(format!("UPDATE experiment_product \
+			SET bb_price_per_mg = v.bb_price_per_mg, bb_lead_time_days = v.bb_lead_time_days \
+			FROM (VALUES {values}) AS v (id, bb_price_per_mg, bb_lead_time_days) \
+			WHERE experiment_product.id = v.id"))
+		.into_boxed::<DB>();
+
+	elems
+		.iter()
+		.fold(query, |query, (id, bb_price_per_mg, bb_lead_time_days)| query
+			.bind::<diesel::sql_types::BigInt, _>(*id)
+			.bind::<diesel::sql_types::Nullable<diesel::sql_types::Float>, _>(*bb_price_per_mg)
+			.bind::<diesel::sql_types::Nullable<diesel::sql_types::Integer>, _>(*bb_lead_time_days))
+		.execute(conn)
+		.map(|_| ())
+}
+
+/// (product id, provider name, refid) of the catalogue compounds of the experiment snapshot having the same structure as a product of the experiment.
+/// Products are matched against the standardized building blocks by SMILES or InChIKey, so salts are ignored.
+pub fn get_experiment_product_catalog_matches(conn: &mut DBConnection, exp: &Experiment) -> QueryResult<Vec<(i64, String, String)>> {
+	// Two equality joins instead of an OR so each one can use its index
+	let by_smiles = experiment_product::table
+		.inner_join(experiment_frag_reactant::table
+		.inner_join(experiment_frag::table
+		.inner_join(experiment::table)))
+		.inner_join(building_block::table.on(building_block::smiles.eq(experiment_product::smiles)))
+		.inner_join(building_block_origin::table.on(building_block_origin::id_building_block.eq(building_block::id)))
+		.inner_join(compound::table.on(compound::id.eq(building_block_origin::id_compound)))
+		.inner_join(compound_provider::table.on(compound_provider::id.eq(compound::id_compound_provider)))
+		.filter(experiment::id.eq(exp.id))
+		.filter(predicate_origin_in_experiment_snapshot())
+		.select((experiment_product::id, compound_provider::name, compound::refid));
+
+	let by_inchikey = experiment_product::table
+		.inner_join(experiment_frag_reactant::table
+		.inner_join(experiment_frag::table
+		.inner_join(experiment::table)))
+		.inner_join(building_block::table.on(building_block::inchikey.eq(experiment_product::inchikey)))
+		.inner_join(building_block_origin::table.on(building_block_origin::id_building_block.eq(building_block::id)))
+		.inner_join(compound::table.on(compound::id.eq(building_block_origin::id_compound)))
+		.inner_join(compound_provider::table.on(compound_provider::id.eq(compound::id_compound_provider)))
+		.filter(experiment::id.eq(exp.id))
+		.filter(predicate_origin_in_experiment_snapshot())
+		.select((experiment_product::id, compound_provider::name, compound::refid));
+
+	by_smiles
+		.union(by_inchikey)
+		.load(conn)
+}
+
 pub fn create_experiment_product_origin(conn: &mut DBConnection, elem: &NewExperimentProductOrigin) -> QueryResult<ExperimentProductOrigin> {
 	diesel::insert_into(experiment_product_origin::table)
 		.values(elem)
@@ -969,6 +1587,15 @@ pub fn delete_moiety(conn: &mut DBConnection, id: i64) -> QueryResult<usize> {
 		.execute(conn)
 }
 
+pub fn upsert_moiety(conn: &mut DBConnection, elem: &NewMoiety) -> QueryResult<Moiety> {
+	diesel::insert_into(moiety::table)
+		.values(elem)
+		.on_conflict(moiety::slug)
+		.do_update()
+		.set(elem)
+		.get_result(conn)
+}
+
 pub fn create_moiety_group(conn: &mut DBConnection, elem: &NewMoietyGroup) -> QueryResult<MoietyGroup> {
 	diesel::insert_into(moiety_group::table)
 		.values(elem)
@@ -993,6 +1620,15 @@ pub fn delete_moiety_group(conn: &mut DBConnection, id: i64) -> QueryResult<usiz
 		.execute(conn)
 }
 
+pub fn upsert_moiety_group(conn: &mut DBConnection, elem: &NewMoietyGroup) -> QueryResult<MoietyGroup> {
+	diesel::insert_into(moiety_group::table)
+		.values(elem)
+		.on_conflict(moiety_group::slug)
+		.do_update()
+		.set(elem)
+		.get_result(conn)
+}
+
 pub fn create_substructure_filter(conn: &mut DBConnection, elem: &NewSubstructureFilter) -> QueryResult<SubstructureFilter> {
 	diesel::insert_into(substructure_filter::table)
 		.values(elem)
@@ -1081,18 +1717,102 @@ pub fn delete_reaction(conn: &mut DBConnection, id: i64) -> QueryResult<usize> {
 		.execute(conn)
 }
 
+pub fn upsert_reaction(conn: &mut DBConnection, elem: &NewReaction) -> QueryResult<Reaction> {
+	diesel::insert_into(reaction::table)
+		.values(elem)
+		.on_conflict(reaction::slug)
+		.do_update()
+		.set(elem)
+		.get_result(conn)
+}
+
+/// The building blocks must be matched again against the reaction, its definition changed.
+pub fn invalidate_reaction_reactants(conn: &mut DBConnection, id: i64) -> QueryResult<usize> {
+	diesel::update(reaction::table)
+		.filter(reaction::id.eq(id))
+		.set(reaction::ts_reactants.eq(None::<NaiveDateTime>))
+		.execute(conn)
+}
+
+pub fn update_reactions_ts_reactants(conn: &mut DBConnection, ids: &[i64], ts: NaiveDateTime) -> QueryResult<usize> {
+	diesel::update(reaction::table)
+		.filter(reaction::id.eq_any(ids))
+		.set(reaction::ts_reactants.eq(ts))
+		.execute(conn)
+}
+
+pub fn create_reaction_incompatible_group(conn: &mut DBConnection, elem: &NewReactionIncompatibleGroup) -> QueryResult<ReactionIncompatibleGroup> {
+	diesel::insert_into(reaction_incompatible_group::table)
+		.values(elem)
+		.get_result(conn)
+}
+
+pub fn update_reaction_incompatible_group(conn: &mut DBConnection, id: i64, elem: &NewReactionIncompatibleGroup) -> QueryResult<ReactionIncompatibleGroup> {
+	diesel::update(reaction_incompatible_group::table)
+		.filter(reaction_incompatible_group::id.eq(id))
+		.set(elem)
+		.get_result(conn)
+}
+
+pub fn get_reaction_incompatible_groups(conn: &mut DBConnection) -> QueryResult<Vec<ReactionIncompatibleGroup>> {
+	reaction_incompatible_group::table
+		.order(reaction_incompatible_group::id)
+		.load(conn)
+}
+
+pub fn delete_reaction_incompatible_group(conn: &mut DBConnection, id: i64) -> QueryResult<usize> {
+	diesel::delete(reaction_incompatible_group::table)
+		.filter(reaction_incompatible_group::id.eq(id))
+		.execute(conn)
+}
+
 pub fn get_building_blocks<'a>(conn: &'a mut DBConnection) -> QueryResult<impl Iterator<Item = QueryResult<BuildingBlock>> + 'a> {
 	building_block::table
 		.select(BuildingBlock::as_select())
 		.load_iter::<_, PgRowByRowLoadingMode>(conn)
 }
 
+pub fn get_building_blocks_pending_reactants<'a>(conn: &'a mut DBConnection) -> QueryResult<impl Iterator<Item = QueryResult<BuildingBlock>> + 'a> {
+	building_block::table
+		.filter(building_block::ts_reactants.is_null())
+		.select(BuildingBlock::as_select())
+		.load_iter::<_, PgRowByRowLoadingMode>(conn)
+}
+
+/// Removes the matches of the building blocks against the reactions, those used by experiment products are only flagged obsolete.
+pub fn delete_building_block_reactants_with_reactions(conn: &mut DBConnection, reaction_ids: &[i64]) -> QueryResult<(usize, usize)> {
+	let used = diesel::dsl::exists(experiment_product_origin::table
+		.filter(experiment_product_origin::id_building_block_reactant.eq(building_block_reactant::id)));
+
+	let deleted_count = diesel::delete(building_block_reactant::table)
+		.filter(building_block_reactant::id_reaction.eq_any(reaction_ids))
+		.filter(diesel::dsl::not(used))
+		.execute(conn)?;
+
+	let obsolete_count = diesel::update(building_block_reactant::table)
+		.filter(building_block_reactant::id_reaction.eq_any(reaction_ids))
+		.filter(building_block_reactant::obsolete.eq(false))
+		.set(building_block_reactant::obsolete.eq(true))
+		.execute(conn)?;
+
+	Ok((deleted_count, obsolete_count))
+}
+
+pub fn update_building_blocks_ts_reactants(conn: &mut DBConnection, ids: &[i64], ts: NaiveDateTime) -> QueryResult<usize> {
+	diesel::update(building_block::table)
+		.filter(building_block::id.eq_any(ids))
+		.set(building_block::ts_reactants.eq(ts))
+		.execute(conn)
+}
+
 impl BuildingBlock {
 	pub fn get_with_reaction<'a>(conn: &'a mut DBConnection, reaction: &Reaction, frag_reactant_idx: i32) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
 		building_block::table
 			.inner_join(building_block_reactant::table)
 			.filter(building_block_reactant::id_reaction.eq(reaction.id))
 			.filter(building_block_reactant::reactant_idx.ne(frag_reactant_idx))
+			.filter(building_block_reactant::excluded.eq(false))
+			.filter(building_block_reactant::obsolete.eq(false))
 			.select(Self::as_select())
 			.load_iter::<_, PgRowByRowLoadingMode>(conn)
 	}
@@ -1113,6 +1833,8 @@ impl ExportableBuildingBlock {
 			.inner_join(compound_provider::table
 			.inner_join(experiment_selected_provider::table))))))))))
 			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
 			.filter(experiment::id.eq(exp.id))
 			.group_by((experiment::id, building_block::id))
 			.select(Self::as_select())
@@ -1132,6 +1854,8 @@ impl ExportableBuildingBlock {
 			.inner_join(compound_provider::table
 			.inner_join(experiment_selected_provider::table))))))))))
 			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
 			.filter(experiment::id.eq(exp.id))
 			.filter(ExperimentProduct::predicate_all_descs(descs))
 			.group_by((experiment::id, building_block::id))
@@ -1153,6 +1877,8 @@ impl ExportableBuildingBlock {
 				.inner_join(compound_provider::table
 				.inner_join(experiment_selected_provider::table))))))))))
 			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
 			.filter(experiment::id.eq(exp_postproc_filter.id_experiment))
 			.filter(experiment_postproc_filter::id.eq(exp_postproc_filter.id))
 			.filter(predicate_experiment_postproc_filter())
@@ -1162,6 +1888,55 @@ impl ExportableBuildingBlock {
 	}
 }
 
+impl PurchasableBuildingBlock {
+	pub fn get_with_experiment<'a>(conn: &'a mut DBConnection, exp: &Experiment) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
+		experiment::table
+			.inner_join(experiment_frag::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_product::table
+			.inner_join(experiment_product_origin::table
+			.inner_join(building_block_reactant::table
+			.inner_join(reaction::table)
+			.inner_join(building_block::table
+			.inner_join(building_block_origin::table
+			.inner_join(compound::table
+			.inner_join(compound_provider::table
+			.inner_join(experiment_selected_provider::table))))))))))
+			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
+			.filter(experiment::id.eq(exp.id))
+			.group_by((building_block::id, compound::id, compound_provider::id, reaction::id))
+			.select(Self::as_select())
+			.load_iter::<_, PgRowByRowLoadingMode>(conn)
+	}
+
+	pub fn get_with_experiment_postproc_filter<'a>(conn: &'a mut DBConnection, exp_postproc_filter: &ExperimentPostprocFilter) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
+		experiment::table
+			.inner_join(experiment_postproc_filter::table)
+			.inner_join(experiment_frag::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_product::table
+			.inner_join(experiment_product_origin::table
+			.inner_join(building_block_reactant::table
+			.inner_join(reaction::table)
+			.inner_join(building_block::table
+			.inner_join(building_block_origin::table
+			.inner_join(compound::table
+			.inner_join(compound_provider::table
+			.inner_join(experiment_selected_provider::table))))))))))
+			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
+			.filter(experiment::id.eq(exp_postproc_filter.id_experiment))
+			.filter(experiment_postproc_filter::id.eq(exp_postproc_filter.id))
+			.filter(predicate_experiment_postproc_filter())
+			.group_by((building_block::id, compound::id, compound_provider::id, reaction::id))
+			.select(Self::as_select())
+			.load_iter::<_, PgRowByRowLoadingMode>(conn)
+	}
+}
+
 fn predicate_experiment_postproc_filter<'a, QS>() -> Box<dyn BoxableExpression<QS, DB, SqlType = diesel::sql_types::Bool>>
 where
 	QS: 'static,
@@ -1177,6 +1952,12 @@ where
 	experiment_product::desc_clogp: BoxableExpression<QS, DB>,
 	experiment_product::desc_mw: BoxableExpression<QS, DB>,
 	experiment_product::desc_tpsa: BoxableExpression<QS, DB>,
+	experiment_postproc_filter::purchasable: BoxableExpression<QS, DB>,
+	experiment_product::purchasable: BoxableExpression<QS, DB>,
+	experiment_postproc_filter::max_price_per_mg: BoxableExpression<QS, DB>,
+	experiment_postproc_filter::max_lead_time_days: BoxableExpression<QS, DB>,
+	experiment_product::bb_price_per_mg: BoxableExpression<QS, DB>,
+	experiment_product::bb_lead_time_days: BoxableExpression<QS, DB>,
 {
 	Box::new(AsExpression::<Bool>::as_expression(true)
 		.and(experiment_postproc_filter::desc_fsp3.contains(experiment_product::desc_fsp3))
@@ -1184,7 +1965,39 @@ where
 		.and(experiment_postproc_filter::desc_hbd.contains(experiment_product::desc_hbd))
 		.and(experiment_postproc_filter::desc_clogp.contains(experiment_product::desc_clogp))
 		.and(experiment_postproc_filter::desc_mw.contains(experiment_product::desc_mw))
-		.and(experiment_postproc_filter::desc_tpsa.contains(experiment_product::desc_tpsa)))
+		.and(experiment_postproc_filter::desc_tpsa.contains(experiment_product::desc_tpsa))
+		.and(coalesce(experiment_postproc_filter::purchasable, experiment_product::purchasable).eq(experiment_product::purchasable))
+		.and(experiment_postproc_filter::max_price_per_mg.is_null()
+			.or(coalesce(experiment_product::bb_price_per_mg.le(experiment_postproc_filter::max_price_per_mg), false)))
+		.and(experiment_postproc_filter::max_lead_time_days.is_null()
+			.or(coalesce(experiment_product::bb_lead_time_days.le(experiment_postproc_filter::max_lead_time_days), false))))
+}
+
+// Vendor compounds listed in the catalog snapshot the experiment ran against
+fn predicate_origin_in_experiment_snapshot<QS>() -> Box<dyn BoxableExpression<QS, DB, SqlType = diesel::sql_types::Bool>>
+where
+	QS: 'static,
+	building_block_origin::id_catalog_snapshot_from: BoxableExpression<QS, DB>,
+	building_block_origin::id_catalog_snapshot_to: BoxableExpression<QS, DB>,
+	experiment::id_catalog_snapshot: BoxableExpression<QS, DB>,
+{
+	Box::new(building_block_origin::id_catalog_snapshot_from.le(experiment::id_catalog_snapshot)
+		.and(coalesce(building_block_origin::id_catalog_snapshot_to, i64::MAX).gt(experiment::id_catalog_snapshot)))
+}
+
+// Vendor compounds within the price and lead time limits of the experiment, unknown values exceed any limit
+fn predicate_compound_within_experiment_limits<QS>() -> Box<dyn BoxableExpression<QS, DB, SqlType = diesel::sql_types::Bool>>
+where
+	QS: 'static,
+	compound::price_per_mg: BoxableExpression<QS, DB>,
+	compound::lead_time_days: BoxableExpression<QS, DB>,
+	experiment::max_price_per_mg: BoxableExpression<QS, DB>,
+	experiment::max_lead_time_days: BoxableExpression<QS, DB>,
+{
+	Box::new(experiment::max_price_per_mg.is_null()
+			.or(coalesce(compound::price_per_mg.le(experiment::max_price_per_mg), false))
+		.and(experiment::max_lead_time_days.is_null()
+			.or(coalesce(compound::lead_time_days.le(experiment::max_lead_time_days), false))))
 }
 
 #[derive(Default, Deserialize)]
@@ -1195,6 +2008,9 @@ pub struct ExperimentProductDescFilter {
 	pub clogp: Option<(f32, f32)>,
 	pub mw: Option<(f32, f32)>,
 	pub tpsa: Option<(f32, f32)>,
+	pub purchasable: Option<bool>,
+	pub max_price_per_mg: Option<f32>,
+	pub max_lead_time_days: Option<i32>,
 }
 
 impl ExperimentProduct {
@@ -1207,6 +2023,9 @@ impl ExperimentProduct {
 		experiment_product::desc_clogp: BoxableExpression<QS, DB>,
 		experiment_product::desc_mw: BoxableExpression<QS, DB>,
 		experiment_product::desc_tpsa: BoxableExpression<QS, DB>,
+		experiment_product::purchasable: BoxableExpression<QS, DB>,
+		experiment_product::bb_price_per_mg: BoxableExpression<QS, DB>,
+		experiment_product::bb_lead_time_days: BoxableExpression<QS, DB>,
 	{
 		let mut expr = boxed_bool(true);
 
@@ -1228,6 +2047,15 @@ impl ExperimentProduct {
 		if let Some(desc) = descs.tpsa {
 			expr = Box::new(expr.and(experiment_product::desc_tpsa.between(desc.0, desc.1)));
 		}
+		if let Some(purchasable) = descs.purchasable {
+			expr = Box::new(expr.and(experiment_product::purchasable.eq(purchasable)));
+		}
+		if let Some(max_price_per_mg) = descs.max_price_per_mg {
+			expr = Box::new(expr.and(coalesce(experiment_product::bb_price_per_mg.le(max_price_per_mg), false)));
+		}
+		if let Some(max_lead_time_days) = descs.max_lead_time_days {
+			expr = Box::new(expr.and(coalesce(experiment_product::bb_lead_time_days.le(max_lead_time_days), false)));
+		}
 	
 		expr
 	}
@@ -1263,6 +2091,28 @@ impl ExperimentProduct {
 			.get_result(conn)
 	}
 
+	// One product per cluster whatever the representative, unclustered products all count
+	pub fn count_representatives_with_experiment_and_descs(conn: &mut DBConnection, exp: &Experiment, descs: &ExperimentProductDescFilter) -> QueryResult<i64> {
+		let unclustered_cnt: i64 = experiment_product::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_frag::table))
+			.filter(experiment_frag::id_experiment.eq(exp.id))
+			.filter(experiment_product::cluster_idx.is_null())
+			.filter(Self::predicate_all_descs(descs))
+			.count()
+			.get_result(conn)?;
+
+		let cluster_cnt: i64 = experiment_product::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_frag::table))
+			.filter(experiment_frag::id_experiment.eq(exp.id))
+			.filter(Self::predicate_all_descs(descs))
+			.select(diesel::dsl::count_distinct(experiment_product::cluster_idx))
+			.get_result(conn)?;
+
+		Ok(unclustered_cnt + cluster_cnt)
+	}
+
 	fn predicate_any_reaction<T: 'static>(reactions: &[Reaction]) -> Box<dyn BoxableExpression<T, DB, SqlType = Bool>>
 	where
 		diesel::dsl::Eq<building_block_reactant::id_reaction, i64>: BoxableExpression<T, DB, SqlType = Bool>
@@ -1324,6 +2174,66 @@ impl ExportableExperimentProduct {
 			.select(Self::as_select())
 			.load_iter::<_, PgRowByRowLoadingMode>(conn)
 	}
+
+	pub fn get_with_experiment_and_ids<'a>(conn: &'a mut DBConnection, exp: &Experiment, ids: &[i64]) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
+		experiment::table
+			.inner_join(experiment_frag::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_product::table)))
+			.filter(experiment::id.eq(exp.id))
+			.filter(experiment_product::id.eq_any(ids.to_vec()))
+			.select(Self::as_select())
+			.load_iter::<_, PgRowByRowLoadingMode>(conn)
+	}
+}
+
+impl ExperimentProductProvenance {
+	pub fn get_with_experiment<'a>(conn: &'a mut DBConnection, exp: &Experiment) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
+		experiment::table
+			.inner_join(experiment_frag::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_product::table
+			.inner_join(experiment_product_origin::table
+			.inner_join(building_block_reactant::table
+			.inner_join(reaction::table)
+			.inner_join(building_block::table
+			.inner_join(building_block_origin::table
+			.inner_join(compound::table
+			.inner_join(compound_provider::table
+			.inner_join(experiment_selected_provider::table))))))))))
+			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
+			.filter(experiment::id.eq(exp.id))
+			.group_by((experiment_product::id, reaction::id, building_block::id))
+			.select(Self::as_select())
+			.load_iter::<_, PgRowByRowLoadingMode>(conn)
+	}
+
+	pub fn get_with_experiment_postproc_filter<'a>(conn: &'a mut DBConnection, exp_postproc_filter: &ExperimentPostprocFilter) -> QueryResult<impl Iterator<Item = QueryResult<Self>> + 'a> {
+		experiment::table
+			.inner_join(experiment_postproc_filter::table)
+			.inner_join(experiment_frag::table
+			.inner_join(experiment_frag_reactant::table
+			.inner_join(experiment_product::table
+			.inner_join(experiment_product_origin::table
+			.inner_join(building_block_reactant::table
+			.inner_join(reaction::table)
+			.inner_join(building_block::table
+			.inner_join(building_block_origin::table
+			.inner_join(compound::table
+			.inner_join(compound_provider::table
+			.inner_join(experiment_selected_provider::table))))))))))
+			.filter(experiment_selected_provider::id_experiment.eq(experiment::id))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
+			.filter(experiment::id.eq(exp_postproc_filter.id_experiment))
+			.filter(experiment_postproc_filter::id.eq(exp_postproc_filter.id))
+			.filter(predicate_experiment_postproc_filter())
+			.group_by((experiment_product::id, reaction::id, building_block::id))
+			.select(Self::as_select())
+			.load_iter::<_, PgRowByRowLoadingMode>(conn)
+	}
 }
 
 impl Reaction {
@@ -1359,6 +2269,10 @@ impl MergedBuildingBlockReactant {
 			.filter(experiment::id.eq(exp.id))
 			.filter(reaction::id.eq(reaction.id)) 
 			.filter(building_block_reactant::reactant_idx.ne(experiment_frag_reactant::reactant_idx))
+			.filter(building_block_reactant::excluded.eq(false))
+			.filter(building_block_reactant::obsolete.eq(false))
+			.filter(predicate_origin_in_experiment_snapshot())
+			.filter(predicate_compound_within_experiment_limits())
 			.group_by((experiment::id, reaction::id, building_block::id, building_block_reactant::id))
 			.select(Self::as_select())
 			.load_iter::<_, PgRowByRowLoadingMode>(conn)
@@ -8,6 +8,11 @@ diesel::table! {
         id -> Int8,
         rdpickle -> Bytea,
         smiles -> Varchar,
+        scaffold_smiles -> Nullable<Varchar>,
+        generic_scaffold_smiles -> Nullable<Varchar>,
+        inchi -> Nullable<Varchar>,
+        inchikey -> Nullable<Varchar>,
+        ts_reactants -> Nullable<Timestamp>,
     }
 }
 
@@ -19,6 +24,8 @@ diesel::table! {
         id -> Int8,
         id_building_block -> Int8,
         id_compound -> Int8,
+        id_catalog_snapshot_from -> Int8,
+        id_catalog_snapshot_to -> Nullable<Int8>,
     }
 }
 
@@ -31,6 +38,35 @@ diesel::table! {
         id_building_block -> Int8,
         id_reaction -> Int8,
         reactant_idx -> Int4,
+        incompatible_groups -> Nullable<Varchar>,
+        excluded -> Bool,
+        obsolete -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::custom::sql_types::*;
+
+    catalog_snapshot (id) {
+        id -> Int8,
+        ts -> Timestamp,
+        standardizer_profile -> Nullable<Jsonb>,
+        import_summary -> Nullable<Jsonb>,
+        ts_completed -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::custom::sql_types::*;
+
+    catalog_snapshot_file (id) {
+        id -> Int8,
+        id_catalog_snapshot -> Int8,
+        id_compound_provider -> Int8,
+        filename -> Varchar,
+        sha256 -> Varchar,
     }
 }
 
@@ -45,6 +81,12 @@ diesel::table! {
         sdf -> Nullable<Varchar>,
         smiles -> Nullable<Varchar>,
         available -> Bool,
+        metadata -> Nullable<Jsonb>,
+        ts_seen -> Nullable<Timestamp>,
+        price_per_mg -> Nullable<Float4>,
+        pack_sizes -> Nullable<Varchar>,
+        in_stock -> Nullable<Bool>,
+        lead_time_days -> Nullable<Int4>,
     }
 }
 
@@ -70,6 +112,11 @@ diesel::table! {
         status -> Varchar,
         ts_start -> Timestamp,
         ts_end -> Nullable<Timestamp>,
+        cluster_cutoff -> Nullable<Float4>,
+        score_profile -> Nullable<Jsonb>,
+        id_catalog_snapshot -> Int8,
+        max_price_per_mg -> Nullable<Float4>,
+        max_lead_time_days -> Nullable<Int4>,
     }
 }
 
@@ -115,6 +162,10 @@ diesel::table! {
         desc_clogp -> Realrange,
         desc_mw -> Realrange,
         desc_tpsa -> Realrange,
+        top_n -> Nullable<Int4>,
+        purchasable -> Nullable<Bool>,
+        max_price_per_mg -> Nullable<Float4>,
+        max_lead_time_days -> Nullable<Int4>,
     }
 }
 
@@ -136,6 +187,22 @@ diesel::table! {
         desc_clogp -> Float4,
         desc_mw -> Float4,
         desc_tpsa -> Float4,
+        cluster_idx -> Nullable<Int4>,
+        cluster_centroid -> Nullable<Bool>,
+        scaffold_smiles -> Nullable<Varchar>,
+        generic_scaffold_smiles -> Nullable<Varchar>,
+        new_ring_systems -> Nullable<Varchar>,
+        score -> Nullable<Float4>,
+        route_smiles -> Nullable<Varchar>,
+        purchasable -> Bool,
+        purchasable_refids -> Nullable<Varchar>,
+        inchi -> Nullable<Varchar>,
+        inchikey -> Nullable<Varchar>,
+        protecting_groups -> Int4,
+        incompatible_groups -> Nullable<Varchar>,
+        bb_price_per_mg -> Nullable<Float4>,
+        bb_lead_time_days -> Nullable<Int4>,
+        cluster_similarity -> Nullable<Float4>,
     }
 }
 
@@ -181,6 +248,7 @@ diesel::table! {
         id -> Int8,
         id_moiety_group -> Int8,
         name -> Varchar,
+        slug -> Varchar,
         rdpickle -> Bytea,
         smarts -> Varchar,
         priority -> Int4,
@@ -194,6 +262,7 @@ diesel::table! {
     moiety_group (id) {
         id -> Int8,
         name -> Varchar,
+        slug -> Varchar,
     }
 }
 
@@ -209,6 +278,22 @@ diesel::table! {
         smarts -> Varchar,
         multistep -> Bool,
         reference -> Nullable<Varchar>,
+        ts_reactants -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::custom::sql_types::*;
+
+    reaction_incompatible_group (id) {
+        id -> Int8,
+        id_reaction -> Int8,
+        reactant_idx -> Nullable<Int4>,
+        name -> Varchar,
+        rdpickle -> Bytea,
+        smarts -> Varchar,
+        exclude -> Bool,
     }
 }
 
@@ -239,7 +324,10 @@ diesel::joinable!(building_block_origin -> building_block (id_building_block));
 diesel::joinable!(building_block_origin -> compound (id_compound));
 diesel::joinable!(building_block_reactant -> building_block (id_building_block));
 diesel::joinable!(building_block_reactant -> reaction (id_reaction));
+diesel::joinable!(catalog_snapshot_file -> catalog_snapshot (id_catalog_snapshot));
+diesel::joinable!(catalog_snapshot_file -> compound_provider (id_compound_provider));
 diesel::joinable!(compound -> compound_provider (id_compound_provider));
+diesel::joinable!(experiment -> catalog_snapshot (id_catalog_snapshot));
 diesel::joinable!(experiment_frag -> experiment (id_experiment));
 diesel::joinable!(experiment_frag -> moiety (id_moiety));
 diesel::joinable!(experiment_frag_reactant -> experiment_frag (id_experiment_frag));
@@ -253,12 +341,15 @@ diesel::joinable!(experiment_selected_provider -> experiment (id_experiment));
 diesel::joinable!(experiment_substructure_filter -> experiment (id_experiment));
 diesel::joinable!(experiment_substructure_filter -> substructure_filter (id_substructure_filter));
 diesel::joinable!(moiety -> moiety_group (id_moiety_group));
+diesel::joinable!(reaction_incompatible_group -> reaction (id_reaction));
 diesel::joinable!(substructure_filter -> substructure_filter_group (id_substructure_filter_group));
 
 diesel::allow_tables_to_appear_in_same_query!(
     building_block,
     building_block_origin,
     building_block_reactant,
+    catalog_snapshot,
+    catalog_snapshot_file,
     compound,
     compound_provider,
     experiment,
@@ -272,6 +363,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     moiety,
     moiety_group,
     reaction,
+    reaction_incompatible_group,
     substructure_filter,
     substructure_filter_group,
 );
@@ -12,10 +12,25 @@ use uuid::Uuid;
 use chemodots_db as db;
 use chemodots_reactor as reactor;
 
+use reactor::cluster::ClusterRepresentative;
+use reactor::grid::{GridExport, GridOptions, GridSortKey};
+
 #[derive(Deserialize)]
 struct FilterQuery {
 	pub uuid: Uuid,
 	pub filters: db::model::ExperimentProductDescFilter,
+	#[serde(default)]
+	pub cluster_representative: Option<ClusterRepresentative>,
+	#[serde(default)]
+	pub top_n: Option<i32>,
+	#[serde(default)]
+	pub provider_preference: Vec<String>,
+	#[serde(default)]
+	pub product_images: bool,
+	#[serde(default)]
+	pub grid_page_size: Option<usize>,
+	#[serde(default)]
+	pub grid_sort: GridSortKey,
 }
 
 fn read_filter_query() -> FilterQuery {
@@ -27,9 +42,29 @@ fn read_filter_query() -> FilterQuery {
 		.expect("Failed to deserialize the query")
 }
 
+#[derive(Deserialize)]
+struct GridPageQuery {
+	pub uuid: Uuid,
+	/// Export holding the pages index, the raw export or the filtered one
+	pub export: GridExport,
+	pub id_reaction: i64,
+	pub page: usize,
+}
+
+fn read_grid_page_query() -> GridPageQuery {
+	let mut contents = Vec::new();
+	io::stdin().read_to_end(&mut contents)
+		.expect("Failed to read stdin");
+
+	serde_json::from_slice(&contents)
+		.expect("Failed to deserialize the query")
+}
+
 #[derive(Deserialize)]
 struct Generate3DQuery {
 	pub uuid: Uuid,
+	#[serde(default)]
+	pub pdbqt_split: bool,
 }
 
 fn read_generate_3d_query() -> Generate3DQuery {
@@ -49,7 +84,12 @@ pub fn filter(db_pool: &db::DBPool) {
 	let ent_exp = db::model::get_experiment_with_uuid(&mut conn, query.uuid).unwrap();
 
 	let total_cnt = db::model::ExperimentProduct::count_with_experiment(&mut conn, &ent_exp).unwrap();
-	let selected_cnt = db::model::ExperimentProduct::count_with_experiment_and_descs(&mut conn, &ent_exp, &query.filters).unwrap();
+	let selected_cnt = if query.cluster_representative.is_some() {
+		db::model::ExperimentProduct::count_representatives_with_experiment_and_descs(&mut conn, &ent_exp, &query.filters).unwrap()
+	} else {
+		db::model::ExperimentProduct::count_with_experiment_and_descs(&mut conn, &ent_exp, &query.filters).unwrap()
+	};
+	let selected_cnt = query.top_n.map_or(selected_cnt, |top_n| selected_cnt.min(top_n.max(0).into()));
 
 	let res_json = json!({
 		"total": total_cnt,
@@ -88,13 +128,51 @@ pub fn generate2d(db_pool: &db::DBPool) {
 		desc_tpsa: f32_filter_to_range(query.filters.tpsa.unwrap_or((f32::NEG_INFINITY, f32::INFINITY))),
 		id_experiment: ent_exp.id,
 		ts: chrono::Utc::now().naive_utc(),
+		top_n: query.top_n,
+		purchasable: query.filters.purchasable,
+		max_price_per_mg: query.filters.max_price_per_mg,
+		max_lead_time_days: query.filters.max_lead_time_days,
 	}).unwrap();
 
-	reactor::gen_files_filtered(&thread_pool, db_pool, &ent_exp, "filtered", "overall_filtered", false, Some(&ent_experiment_postproc_filter));
+	let opts = reactor::ExportOptions {
+		gen_img: false,
+		gen_product_img: query.product_images,
+		cluster_representative: query.cluster_representative,
+		provider_preference: query.provider_preference,
+		grid: Some(GridOptions {
+			page_size: query.grid_page_size.unwrap_or(GridOptions::default().page_size),
+			sort: query.grid_sort,
+			..Default::default()
+		}),
+	};
+
+	reactor::gen_files_filtered(&thread_pool, db_pool, &ent_exp, "filtered", "overall_filtered", &opts, Some(&ent_experiment_postproc_filter));
+
+	// The report also lists the filter history
+	reactor::report::gen_report(db_pool, &ent_exp)
+		.unwrap_or_else(|err| eprintln!("{err}"));
 
 	println!("{{}}");
 }
 
+pub fn grid_page(db_pool: &db::DBPool) {
+	let mut conn = db_pool.get().unwrap();
+
+	let query = read_grid_page_query();
+
+	let ent_exp = db::model::get_experiment_with_uuid(&mut conn, query.uuid).unwrap();
+
+	std::env::set_current_dir(ent_exp.uuid.to_string()).unwrap();
+
+	let file = reactor::grid::gen_grid_page(db_pool, &ent_exp, query.export, query.id_reaction, query.page)
+		.unwrap_or_else(|err| panic!("{err}"));
+
+	let res_json = json!({
+		"file": file,
+	});
+	println!("{}", res_json.to_string());
+}
+
 pub fn generate3d(db_pool: &db::DBPool) {
 	let thread_pool = rayon::ThreadPoolBuilder::new()
 		.num_threads(0)
@@ -112,7 +190,11 @@ pub fn generate3d(db_pool: &db::DBPool) {
 	let ent_experiment_postproc_filter = db::model::get_last_experiment_postproc_filter_with_experiment(&mut conn, &ent_exp)
 		.unwrap();
 
-	reactor::gen_files_filtered_3d(&thread_pool, db_pool, &ent_exp, "filtered_3d", "overall_filtered", &ent_experiment_postproc_filter);
+	let opts = reactor::Export3DOptions {
+		pdbqt_split: query.pdbqt_split,
+	};
+
+	reactor::gen_files_filtered_3d(&thread_pool, db_pool, &ent_exp, "filtered_3d", "overall_filtered", &opts, &ent_experiment_postproc_filter);
 
 	println!("{{}}");
 }
@@ -0,0 +1,76 @@
+use std::collections::BTreeSet;
+
+use itertools::Itertools;
+
+use chemodots_db as db;
+
+use db::model::Experiment;
+
+/// Flags the products of the experiment which are sold as catalogue compounds, along with their vendor refids.
+pub(crate) fn experiment_match_catalog(db_pool: &db::DBPool, ent_experiment: &Experiment) {
+	let mut conn = db_pool.get().unwrap();
+
+	let matches = db::model::get_experiment_product_catalog_matches(&mut conn, ent_experiment)
+		.unwrap();
+
+	// A product can match several compounds, sold by one or more vendors
+	let elems = matches
+		.into_iter()
+		.into_group_map_by(|(id, _, _)| *id)
+		.into_iter()
+		.map(|(id, rows)| {
+			let refids = rows
+				.into_iter()
+				.map(|(_, provider, refid)| format!("{provider}:{refid}"))
+				.collect::<BTreeSet<_>>()
+				.into_iter()
+				.join(",");
+
+			(id, refids)
+		})
+		.collect_vec();
+
+	eprintln!("  {} purchasable products.", elems.len());
+
+	// Bind parameters of a statement are limited
+	elems
+		.chunks(4096)
+		.try_for_each(|chunk| db::model::update_experiment_product_purchasables(&mut conn, chunk))
+		.unwrap();
+}
+
+/// Records on each product the lowest price per mg and lead time among the building blocks it can be made from.
+/// Each origin of a product is an alternative route, building blocks of unknown price (or lead time) are skipped.
+pub(crate) fn experiment_summarize_offers(db_pool: &db::DBPool, ent_experiment: &Experiment) {
+	let mut conn = db_pool.get().unwrap();
+
+	let offers = db::model::get_experiment_product_building_block_offers(&mut conn, ent_experiment)
+		.unwrap();
+
+	let elems = offers
+		.into_iter()
+		.into_group_map_by(|(id, _, _, _)| *id)
+		.into_iter()
+		.map(|(id, rows)| {
+			let price_per_mg = rows
+				.iter()
+				.filter_map(|(_, _, price_per_mg, _)| *price_per_mg)
+				.reduce(f32::min);
+			let lead_time_days = rows
+				.iter()
+				.filter_map(|(_, _, _, lead_time_days)| *lead_time_days)
+				.min();
+
+			(id, price_per_mg, lead_time_days)
+		})
+		.filter(|(_, price_per_mg, lead_time_days)| price_per_mg.is_some() || lead_time_days.is_some())
+		.collect_vec();
+
+	eprintln!("  {} products with known building block offers.", elems.len());
+
+	// Bind parameters of a statement are limited
+	elems
+		.chunks(4096)
+		.try_for_each(|chunk| db::model::update_experiment_product_offers(&mut conn, chunk))
+		.unwrap();
+}
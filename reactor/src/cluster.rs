@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use rayon::{prelude::*, ThreadPool};
+use serde::Deserialize;
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::fingerprints::morgan::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::prelude::*;
+
+use chemodots_db as db;
+
+use db::model::{Experiment, ExperimentProduct};
+
+const FP_RADIUS: u32 = 2;
+const FP_SIZE: usize = 2048;
+const FP_WORDS: usize = FP_SIZE / 64;
+
+/// How a single product is picked from each cluster when exporting representatives only.
+/// Unclustered products are always exported.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterRepresentative {
+	/// The member most similar to the centroid when the centroid itself is filtered out
+	Centroid,
+	LowestMw,
+	BestScore,
+}
+
+struct Fingerprint {
+	words: [u64; FP_WORDS],
+	count: u32,
+}
+
+impl Fingerprint {
+	fn from_on_bits(on_bits: &[u32]) -> Self {
+		let mut words = [0u64; FP_WORDS];
+
+		for bit in on_bits {
+			let bit = *bit as usize % FP_SIZE;
+			words[bit / 64] |= 1 << (bit % 64);
+		}
+
+		let count = words
+			.iter()
+			.map(|w| w.count_ones())
+			.sum();
+
+		Self { words, count }
+	}
+
+	fn tanimoto(&self, other: &Self) -> f32 {
+		let common: u32 = self.words
+			.iter()
+			.zip(other.words.iter())
+			.map(|(a, b)| (a & b).count_ones())
+			.sum();
+
+		let union = self.count + other.count - common;
+		if union == 0 {
+			return 1.0;
+		}
+
+		common as f32 / union as f32
+	}
+}
+
+/// Butina (1999) sphere exclusion clustering.
+/// `cutoff` is a Tanimoto distance, returns (cluster_idx, is_centroid) for each fingerprint.
+/// Clusters are numbered by decreasing size of their initial neighbourhood.
+fn butina(fps: &[Fingerprint], cutoff: f32) -> Vec<(i32, bool)> {
+	let min_sim = 1.0 - cutoff;
+	let fp_count = fps.len();
+
+	let is_neighbour = |i: usize, j: usize| j != i && fps[i].tanimoto(&fps[j]) >= min_sim;
+
+	// Only the neighbourhood sizes are kept, all the lists would take O(n²) memory with a loose cutoff
+	let neighbour_counts: Vec<usize> = (0..fp_count)
+		.into_par_iter()
+		.map(|i| (0..fp_count)
+			.filter(|&j| is_neighbour(i, j))
+			.count())
+		.collect();
+
+	let order = (0..fp_count)
+		.sorted_by_key(|&i| std::cmp::Reverse(neighbour_counts[i]))
+		.collect_vec();
+
+	let mut assigned: Vec<Option<(i32, bool)>> = vec![None; fp_count];
+	let mut cluster_idx = 0;
+
+	for i in order {
+		if assigned[i].is_some() {
+			continue;
+		}
+
+		assigned[i] = Some((cluster_idx, true));
+
+		// Neighbours of the centroid are found again when it is picked
+		let neighbours: Vec<usize> = (0..fp_count)
+			.into_par_iter()
+			.filter(|&j| assigned[j].is_none() && is_neighbour(i, j))
+			.collect();
+
+		for j in neighbours {
+			assigned[j] = Some((cluster_idx, false));
+		}
+
+		cluster_idx += 1;
+	}
+
+	assigned
+		.into_iter()
+		.map(|e| e.unwrap())
+		.collect()
+}
+
+pub fn experiment_cluster_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, cutoff: f32) {
+	let mut conn = db_pool.get().unwrap();
+
+	eprintln!("Clustering products...");
+
+	let ent_products = ExperimentProduct::get_with_experiment(&mut conn, ent_experiment)
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.collect_vec();
+
+	thread_pool.install(|| {
+		let (ids, fps): (Vec<_>, Vec<_>) = ent_products
+			.par_iter()
+			.map(|ent_product| -> Result<_, String> {
+				let prod_mol = new_local!(ROMol);
+				let prod_mol = prod_mol
+					.init(ROMolFromPickleParams {
+						pickle: &ent_product.rdpickle
+					})
+					.unwrap();
+
+				let on_bits = prod_mol
+					.get_morgan_fingerprint_on_bits(&MorganFingerprintParams {
+						radius: FP_RADIUS,
+						n_bits: FP_SIZE as u32,
+						..Default::default()
+					})
+					.map_err(|_| format!("Failed to compute fingerprint of product '{}'", ent_product.fullname))?;
+
+				Ok((ent_product.id, Fingerprint::from_on_bits(&on_bits)))
+			})
+			.filter_map(|e| {
+				if let Err(err) = &e {
+					eprintln!("{err}");
+				}
+				e.ok()
+			})
+			.unzip();
+
+		let clusters = butina(&fps, cutoff);
+
+		eprintln!("  {} clusters for {} products.", clusters.iter().filter(|(_, centroid)| *centroid).count(), ids.len());
+
+		// Similarity of each member to the centroid of its cluster
+		let centroids: HashMap<i32, &Fingerprint> = clusters
+			.iter()
+			.zip(fps.iter())
+			.filter(|((_, cluster_centroid), _)| *cluster_centroid)
+			.map(|((cluster_idx, _), fp)| (*cluster_idx, fp))
+			.collect();
+
+		let elems = ids
+			.into_iter()
+			.zip(clusters.iter())
+			.zip(fps.iter())
+			.map(|((id, &(cluster_idx, cluster_centroid)), fp)| (id, cluster_idx, cluster_centroid, fp.tanimoto(centroids[&cluster_idx])))
+			.collect_vec();
+
+		elems
+			.par_chunks(4096)
+			.for_each(|chunk| {
+				let mut conn = db_pool.get().unwrap();
+				db::model::update_experiment_product_clusters(&mut conn, chunk)
+					.expect("Failed to store product clusters");
+			});
+	});
+
+	eprintln!(" completed.");
+}
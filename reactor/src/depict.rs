@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::depictor::DepictorMutImpl;
+use rdkit_rust::graphmol::moldraw2d::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dcairo::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::graphmol::rwmol::*;
+use rdkit_rust::prelude::*;
+
+pub const PRODUCT_IMG_SIZE: u32 = 400;
+
+pub struct ProductDepiction {
+	pub svg: String,
+	pub png: Vec<u8>,
+}
+
+fn parse_smiles(smiles: &str) -> Option<InitializedHeap<'static, RWMol>> {
+	RWMol::new(ParseSmilesParams {
+			text: smiles,
+			debug_parse: Default::default(),
+			sanitize: Default::default(),
+			replacements: (),
+		})
+		.ok()
+}
+
+fn map_nums(mol: &impl ROMolImplRef) -> HashSet<i32> {
+	(0..mol.get_num_atoms())
+		.filter_map(|idx_atom| mol.get_atom(idx_atom).map(|atom| atom.get_atom_map_num()).ok())
+		.filter(|map_num| *map_num != 0)
+		.collect()
+}
+
+/// Depiction of the product of a mapped route (see `route::mapped_reaction_smiles`).
+/// Atoms coming from the fragment (first reactant) and bonds formed between the fragment and the building block are highlighted.
+/// `frag_components` is the number of components of the fragment (e.g. 2 for a salt), the following ones belong to the building block.
+pub fn depict_product(route_smiles: &str, frag_components: usize, legend: &str) -> Option<ProductDepiction> {
+	let (reactants, product) = route_smiles.split_once(">>")?;
+
+	let components: Vec<&str> = reactants.split('.').collect();
+	if components.len() <= frag_components {
+		return None;
+	}
+	let (frag, bb) = components.split_at(frag_components);
+
+	let frag_map_nums = map_nums(&parse_smiles(&frag.join("."))?);
+	let bb_map_nums = map_nums(&parse_smiles(&bb.join("."))?);
+
+	let mut mol = parse_smiles(product)?;
+
+	let mut atom_map_nums = Vec::new();
+	for idx_atom in 0..mol.get_num_atoms() {
+		let mut atom = mol.get_atom_mut(idx_atom).ok()?;
+		atom_map_nums.push(atom.get_atom_map_num());
+		// Map numbers would otherwise be drawn next to the atoms
+		atom.set_atom_map_num(0);
+	}
+
+	let highlight_atoms: Vec<u32> = (0..mol.get_num_atoms())
+		.filter(|idx_atom| frag_map_nums.contains(&atom_map_nums[*idx_atom as usize]))
+		.collect();
+
+	let highlight_bonds: Vec<u32> = (0..mol.get_num_bonds())
+		.filter(|idx_bond| {
+			let bond = mol.get_bond(*idx_bond).unwrap();
+			let map_num_begin = atom_map_nums[bond.get_begin_atom_idx() as usize];
+			let map_num_end = atom_map_nums[bond.get_end_atom_idx() as usize];
+
+			(frag_map_nums.contains(&map_num_begin) && bb_map_nums.contains(&map_num_end))
+				|| (bb_map_nums.contains(&map_num_begin) && frag_map_nums.contains(&map_num_end))
+		})
+		.collect();
+
+	mol.compute_2d_coords();
+
+	let canvas = new_local!(MolDraw2DSVG);
+	let mut canvas = canvas
+		.init(&MolDraw2DSVGInitParams {
+			width: PRODUCT_IMG_SIZE,
+			height: PRODUCT_IMG_SIZE,
+			panel_width: None,
+			panel_height: None,
+		})
+		.ok()?;
+
+	canvas.draw_molecule_with_highlights(&mol, Some(legend), &highlight_atoms, &highlight_bonds);
+	canvas.finish_drawing();
+
+	let svg = canvas
+		.get_drawing_text()
+		.ok()?;
+
+	let canvas = new_local!(MolDraw2DCairo);
+	let mut canvas = canvas
+		.init(&MolDraw2DCairoInitParams {
+			width: PRODUCT_IMG_SIZE,
+			height: PRODUCT_IMG_SIZE,
+			panel_width: None,
+			panel_height: None,
+		})
+		.ok()?;
+
+	canvas.draw_molecule_with_highlights(&mol, Some(legend), &highlight_atoms, &highlight_bonds);
+	canvas.finish_drawing();
+
+	let png = canvas
+		.get_drawing_data()
+		.ok()?;
+
+	Some(ProductDepiction { svg, png })
+}
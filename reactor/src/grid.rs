@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::moldraw2d::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
+use rdkit_rust::graphmol::romol::*;
+
+use chemodots_db as db;
+
+use db::model::{Experiment, ExportableExperimentProduct, Reaction};
+
+use crate::{truncate_legend, ExportedProduct};
+
+const GRID_COLUMNS: usize = 10;
+const GRID_PANEL_SIZE: usize = 256;
+
+pub const PAGES_INDEX_FILENAME: &str = "pages.json";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GridSortKey {
+	/// Same order as the exported files (score, then MW)
+	#[default]
+	Rank,
+	Mw,
+	Clogp,
+	Tpsa,
+	Fsp3,
+	Name,
+}
+
+#[derive(Clone, Debug)]
+pub struct GridOptions {
+	pub page_size: usize,
+	pub sort: GridSortKey,
+	/// Pages drawn at export time for each reaction, the following ones are drawn on demand
+	pub eager_pages: usize,
+}
+
+impl Default for GridOptions {
+	fn default() -> Self {
+		Self {
+			page_size: 100,
+			sort: GridSortKey::Rank,
+			eager_pages: 10,
+		}
+	}
+}
+
+/// Export the pages belong to, each one is written in a fixed directory of the experiment.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GridExport {
+	Raw,
+	Filtered,
+}
+
+impl GridExport {
+	pub fn dir(self) -> &'static str {
+		match self {
+			Self::Raw => "raw",
+			Self::Filtered => "filtered",
+		}
+	}
+}
+
+fn page_filename(id_reaction: i64, idx_page: usize) -> String {
+	format!("reaction{id_reaction}-page{idx_page}.svg")
+}
+
+fn draw_page<'a>(mols: &[&InitializedHeap<'a, ROMol>], legends: &[&str]) -> String {
+	let rows = mols.len().div_ceil(GRID_COLUMNS).max(1);
+
+	let canvas = new_local!(MolDraw2DSVG);
+	let mut canvas = canvas
+		.init(&MolDraw2DSVGInitParams {
+			width: (GRID_COLUMNS * GRID_PANEL_SIZE) as _,
+			height: (rows * GRID_PANEL_SIZE) as _,
+			panel_width: Some(GRID_PANEL_SIZE as _),
+			panel_height: Some(GRID_PANEL_SIZE as _),
+		})
+		.unwrap();
+
+	if !mols.is_empty() {
+		canvas.draw_molecules(mols, Some(legends));
+	}
+
+	canvas.finish_drawing();
+
+	canvas
+		.get_drawing_text()
+		.unwrap()
+}
+
+/// Splits the products of each reaction into pages, draws the first pages and writes the pages index.
+pub(crate) fn gen_grid_pages(path_prefix: &Path, exp_uuid_str: &str, opts: &GridOptions, ent_reactions: &[Reaction], products: &[ExportedProduct]) {
+	let page_size = opts.page_size.max(1);
+
+	let json_reactions = ent_reactions
+		.iter()
+		.unique_by(|ent_reaction| ent_reaction.id)
+		.sorted_by_key(|ent_reaction| ent_reaction.id)
+		.map(|ent_reaction| {
+			let mut prods = products
+				.iter()
+				.filter(|prod| prod.id_reaction == ent_reaction.id)
+				.collect_vec();
+
+			// Products are already in rank order, the sort is stable
+			match opts.sort {
+				GridSortKey::Rank => (),
+				GridSortKey::Mw => prods.sort_by(|prod0, prod1| f64::total_cmp(&prod0.mw, &prod1.mw)),
+				GridSortKey::Clogp => prods.sort_by(|prod0, prod1| f32::total_cmp(&prod0.clogp, &prod1.clogp)),
+				GridSortKey::Tpsa => prods.sort_by(|prod0, prod1| f32::total_cmp(&prod0.tpsa, &prod1.tpsa)),
+				GridSortKey::Fsp3 => prods.sort_by(|prod0, prod1| f32::total_cmp(&prod0.fsp3, &prod1.fsp3)),
+				GridSortKey::Name => prods.sort_by(|prod0, prod1| prod0.fullname.cmp(&prod1.fullname)),
+			}
+
+			let pages = prods
+				.chunks(page_size)
+				.collect_vec();
+
+			pages
+				.iter()
+				.take(opts.eager_pages)
+				.enumerate()
+				.for_each(|(idx_page, page)| {
+					let (mols, legends): (Vec<_>, Vec<_>) = page
+						.iter()
+						.map(|prod| (&prod.mol, prod.legend.as_str()))
+						.unzip();
+
+					let img_text = draw_page(&mols, &legends);
+
+					fs::write(Path::join(path_prefix, page_filename(ent_reaction.id, idx_page)), img_text.as_bytes())
+						.expect(&format!("Failed to write products page {idx_page} SVG file for experiment {exp_uuid_str} and reaction {}", ent_reaction.id));
+				});
+
+			let json_pages = pages
+				.iter()
+				.enumerate()
+				.map(|(idx_page, page)| json!({
+					"idx": idx_page,
+					"file": page_filename(ent_reaction.id, idx_page),
+					"generated": idx_page < opts.eager_pages,
+					"product_ids": page.iter().map(|prod| prod.id).collect_vec(),
+				}))
+				.collect_vec();
+
+			json!({
+				"id": ent_reaction.id,
+				"name": ent_reaction.name,
+				"slug": ent_reaction.slug,
+				"count": prods.len(),
+				"pages": json_pages,
+			})
+		})
+		.collect_vec();
+
+	let res_json = json!({
+		"page_size": page_size,
+		"sort": opts.sort,
+		"reactions": json_reactions,
+	});
+
+	fs::write(Path::join(path_prefix, PAGES_INDEX_FILENAME), res_json.to_string().as_bytes())
+		.expect(&format!("Failed to write pages index for experiment {exp_uuid_str}"));
+}
+
+// Written next to the file then renamed over it, so readers never see a partial file
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+	let mut path_tmp = path.as_os_str().to_owned();
+	path_tmp.push(format!(".{}.tmp", std::process::id()));
+
+	fs::write(&path_tmp, contents)?;
+	fs::rename(&path_tmp, path)
+}
+
+fn read_pages_index(path_index: &Path, exp_uuid_str: &str) -> Result<Value, String> {
+	fs::read(path_index)
+		.map_err(|_| format!("Missing pages index for experiment {exp_uuid_str}"))
+		.and_then(|contents| serde_json::from_slice(&contents)
+			.map_err(|_| format!("Invalid pages index for experiment {exp_uuid_str}")))
+}
+
+fn find_page(index: &mut Value, id_reaction: i64, idx_page: usize) -> Option<&mut Value> {
+	index["reactions"]
+		.as_array_mut()
+		.and_then(|reactions| reactions
+			.iter_mut()
+			.find(|reaction| reaction["id"].as_i64() == Some(id_reaction)))
+		.and_then(|reaction| reaction["pages"].get_mut(idx_page))
+}
+
+/// Draws a page listed in the pages index of `export` if it was not drawn yet, returns its file name.
+pub fn gen_grid_page(db_pool: &db::DBPool, ent_experiment: &Experiment, export: GridExport, id_reaction: i64, idx_page: usize) -> Result<String, String> {
+	let exp_uuid_str = ent_experiment.uuid.to_string();
+	let path_prefix = Path::new(export.dir());
+	let path_index = Path::join(path_prefix, PAGES_INDEX_FILENAME);
+
+	let mut index = read_pages_index(&path_index, &exp_uuid_str)?;
+
+	let json_page = find_page(&mut index, id_reaction, idx_page)
+		.ok_or_else(|| format!("No page {idx_page} for experiment {exp_uuid_str} and reaction {id_reaction}"))?;
+
+	let filename = page_filename(id_reaction, idx_page);
+	let path_page = Path::join(path_prefix, &filename);
+
+	// Also drawn when a concurrent request could not record it in the index
+	if json_page["generated"].as_bool() == Some(true) || path_page.exists() {
+		return Ok(filename);
+	}
+
+	let ids = json_page["product_ids"]
+		.as_array()
+		.map(|ids| ids.iter().filter_map(|id| id.as_i64()).collect_vec())
+		.unwrap_or_default();
+
+	let mut conn = db_pool.get().unwrap();
+
+	let mut ent_products: HashMap<_, _> = ExportableExperimentProduct::get_with_experiment_and_ids(&mut conn, ent_experiment, &ids)
+		.map_err(|err| format!("Failed to fetch products of page {idx_page}: {err:?}"))?
+		.filter_map(|e| e.ok())
+		.map(|e| (e.id, e))
+		.collect();
+
+	// Keep the order of the index
+	let (mols, legends): (Vec<_>, Vec<_>) = ids
+		.iter()
+		.filter_map(|id| ent_products.remove(id))
+		.collect_vec()
+		.into_par_iter()
+		.map(|ent_product| {
+			let prod_mol = ROMol::new(ROMolFromPickleParams {
+					pickle: &ent_product.rdpickle
+				})
+				.unwrap();
+
+			(prod_mol, truncate_legend(ent_product.name))
+		})
+		.collect::<Vec<_>>()
+		.into_iter()
+		.unzip();
+
+	let mols_ref = mols.iter().collect_vec();
+	let legends_ref = legends.iter().map(|e| e.as_str()).collect_vec();
+
+	let img_text = draw_page(&mols_ref, &legends_ref);
+
+	write_atomic(&path_page, img_text.as_bytes())
+		.map_err(|_| format!("Failed to write products page {idx_page} SVG file for experiment {exp_uuid_str} and reaction {id_reaction}"))?;
+
+	// Read again right before replacing it, to keep the pages drawn by concurrent requests meanwhile
+	let mut index = read_pages_index(&path_index, &exp_uuid_str)?;
+
+	if let Some(json_page) = find_page(&mut index, id_reaction, idx_page) {
+		json_page["generated"] = json!(true);
+	}
+
+	write_atomic(&path_index, index.to_string().as_bytes())
+		.map_err(|_| format!("Failed to update pages index for experiment {exp_uuid_str}"))?;
+
+	Ok(filename)
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{File, self};
 use std::io::{Write, self};
 use std::path::Path;
@@ -30,19 +30,45 @@ use rdkit_rust::prelude::*;
 use chemodots_db as db;
 use chemodots_common as common;
 
-use db::model::{CompoundProvider, ExperimentPostprocFilter, ExperimentProductDescFilter, NewExperiment, NewExperimentFrag, NewExperimentFragReactant, NewExperimentPostprocFilter, NewExperimentProductOrigin, NewExperimentSelectedProvider, Reaction};
+use db::model::{CompoundProvider, ExperimentPostprocFilter, ExperimentProductProvenance, ExperimentProductDescFilter, NewExperiment, NewExperimentFrag, NewExperimentFragReactant, NewExperimentPostprocFilter, NewExperimentProductOrigin, NewExperimentSelectedProvider, Reaction};
 use db::model::{Experiment, NewExperimentProduct};
 
-pub mod plot;
+use cluster::ClusterRepresentative;
+use grid::GridOptions;
+use score::ScoreProfile;
 
-pub fn experiment_create(db_pool: &db::DBPool, exp_name: &str, frag_smiles: &str, frag_mol: Option<&str>, idx_atoms: &[i32], id_reactions: &[i64], provider_names: &[&str]) -> Experiment {
+mod catalog;
+pub mod cluster;
+pub mod depict;
+pub mod grid;
+pub mod plot;
+pub mod pdbqt;
+mod purchase;
+pub mod report;
+pub mod route;
+mod scaffold;
+pub mod score;
+
+pub fn experiment_create(db_pool: &db::DBPool, exp_name: &str, frag_smiles: &str, frag_mol: Option<&str>, idx_atoms: &[i32], id_reactions: &[i64], provider_names: &[&str], cluster_cutoff: Option<f32>, score_profile: Option<&ScoreProfile>, id_catalog_snapshot: Option<i64>, max_price_per_mg: Option<f32>, max_lead_time_days: Option<i32>) -> Experiment {
 	let mut conn = db_pool.get().unwrap();
 
+	let score_profile = score_profile
+		.map(|e| serde_json::to_value(e).unwrap());
+
+	// Experiments run against the latest catalog unless asked to reproduce a previous one
+	let id_catalog_snapshot = id_catalog_snapshot
+		.unwrap_or_else(|| db::model::get_last_catalog_snapshot(&mut conn).unwrap().id);
+
 	let ent_experiment = db::model::create_experiment(&mut conn, &NewExperiment {
 		name: exp_name,
 		status: "",
 		ts_start: chrono::Utc::now().naive_utc(),
 		ts_end: None,
+		cluster_cutoff,
+		score_profile: score_profile.as_ref(),
+		id_catalog_snapshot,
+		max_price_per_mg,
+		max_lead_time_days,
 	}).unwrap();
 
 	{
@@ -171,6 +197,7 @@ pub struct ReactionCounterAtomic {
 	pub reacted_building_blocks: AtomicUsize,
 	pub raw_products: AtomicUsize,
 	pub dup_products: AtomicUsize,
+	pub shared_products: AtomicUsize,
 	pub undesired_products: AtomicUsize,
 	pub final_products: AtomicUsize,
 }
@@ -180,6 +207,8 @@ pub struct ReactionCounter {
 	pub reacted_building_blocks: usize,
 	pub raw_products: usize,
 	pub dup_products: usize,
+	/// Final products already generated through a previous reaction, they are only stored once
+	pub shared_products: usize,
 	pub undesired_products: usize,
 	pub final_products: usize,
 }
@@ -190,6 +219,7 @@ impl From<ReactionCounterAtomic> for ReactionCounter {
 			reacted_building_blocks: value.reacted_building_blocks.load(Ordering::Relaxed),
 			raw_products: value.raw_products.load(Ordering::Relaxed),
 			dup_products: value.dup_products.load(Ordering::Relaxed),
+			shared_products: value.shared_products.load(Ordering::Relaxed),
 			undesired_products: value.undesired_products.load(Ordering::Relaxed),
 			final_products: value.final_products.load(Ordering::Relaxed),
 		}
@@ -206,19 +236,58 @@ pub struct ReactionResult {
 #[derive(Clone, Debug, Default)]
 pub struct ExperimentGenProductsResult {
 	pub reactions: Vec<ReactionResult>,
+	/// Distinct products over all the reactions
+	pub distinct_products: usize,
 }
 
-pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &mut Experiment) -> ExperimentGenProductsResult {
+struct GeneratedProduct {
+	key: String,
+	id_frag_reactant: i64,
+	id_bb_reactant: i64,
+	name: String,
+	fullname: String,
+	smiles: String,
+	pickle: Vec<u8>,
+	dup_count: usize,
+	fsp3: f32,
+	hba: i32,
+	hbd: i32,
+	clogp: f32,
+	mw: f32,
+	tpsa: f32,
+	scaffolds: Option<common::scaffold::Scaffolds>,
+	new_ring_systems: String,
+	score: Option<f32>,
+	route_smiles: Option<String>,
+	identifiers: Option<common::identifier::Identifiers>,
+	protecting_groups: i32,
+	incompatible_groups: Option<String>,
+}
+
+fn protecting_group_count(incompatible_groups: Option<&str>) -> i32 {
+	incompatible_groups
+		.map_or(0, |groups| groups.split(',').count() as i32)
+}
+
+pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &mut Experiment, opts: &ExportOptions) -> ExperimentGenProductsResult {
 	let mut conn = db_pool.get().unwrap();
 
 	let mut result = ExperimentGenProductsResult::default();
 
+	let score_profile: Option<ScoreProfile> = ent_experiment.score_profile
+		.as_ref()
+		.map(|e| serde_json::from_value(e.clone()).expect("Invalid score profile"));
+	let score_profile = &score_profile;
+
 	let ent_frag_reactants = db::model::MergedExperimentFragReactant::get_with_experiment_and_idx(&mut conn, &ent_experiment, 0)
 		.unwrap()
 		.filter_map(|e| e.ok());
 
 	let mut reaction_infos = HashMap::<i64, (Reaction, ReactionCounterAtomic)>::default();
 
+	// Product id of each duplicate key (InChIKey or SMILES), shared by all the reactions
+	let mut product_ids = HashMap::<String, i64>::default();
+
 	for ent_frag_reactant in ent_frag_reactants {
 		let ent_reaction = db::model::get_reaction(&mut conn, ent_frag_reactant.id_reaction)
 			.unwrap();
@@ -232,6 +301,7 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 		let counter_reacted_building_blocks = &reaction_counter.reacted_building_blocks;
 		let counter_raw_products = &reaction_counter.raw_products;
 		let counter_dup_products = &reaction_counter.dup_products;
+		let counter_shared_products = &reaction_counter.shared_products;
 		let _counter_undesired_products = &reaction_counter.undesired_products;
 		let counter_final_products = &reaction_counter.final_products;
 
@@ -252,6 +322,9 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 			atom.set_prop_i32("_protected", 1);
 		}
 
+		let frag_ring_systems = common::scaffold::ring_system_smiles(&frag_mol);
+		let frag_ring_systems = &frag_ring_systems;
+
 		eprintln!("  Generating products...");
 
 		thread_pool.in_place_scope(|scope| {
@@ -347,7 +420,21 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 									if let Some((found_smiles, found_product)) = found {
 										counter_reacted_building_blocks.fetch_add(1, Ordering::Relaxed);
 
-										tx.send((found_smiles, (found_product, ent_frag_reactant.id, ent_bb_reactant.id, ent_bb_reactant.name, ent_bb_reactant.fullname))).unwrap();
+										let idx_frag = ent_frag_reactant.reactant_idx.try_into().unwrap();
+										let idx_bb = ent_bb_reactant.reactant_idx.try_into().unwrap();
+
+										let route_smiles = route::mapped_reaction_smiles(
+											reaction,
+											&[(idx_frag, &reactants.get(idx_frag).unwrap()), (idx_bb, &reactants.get(idx_bb).unwrap())],
+											&found_product);
+
+										// Duplicates are identified by InChIKey, which also merges alternate SMILES (e.g. mobile hydrogens) of the same compound
+										let identifiers = common::identifier::inchi_identifiers(&found_product);
+										let key = identifiers
+											.as_ref()
+											.map_or_else(|| found_smiles.clone(), |e| e.inchikey.clone());
+
+										tx.send((key, (found_product, ent_frag_reactant.id, ent_bb_reactant.id, ent_bb_reactant.name, ent_bb_reactant.fullname, route_smiles, found_smiles, identifiers, ent_bb_reactant.incompatible_groups))).unwrap();
 										return Some(());
 									}
 
@@ -358,25 +445,77 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 				});
 			});
 
-			let mut grouped = rx
+			let grouped = rx
 				.into_iter()
 				.into_group_map();
 
 			eprintln!("   completed.");
 			eprintln!("  Inserting products...");
 
-			grouped
+			// Products already generated by a previous reaction only get a new origin
+			let (shared, mut grouped): (HashMap<_, _>, HashMap<_, _>) = grouped
+				.into_iter()
+				.partition(|(key, _)| product_ids.contains_key(key));
+
+			let shared = shared
+				.into_iter()
+				.filter_map(|(key, v)| {
+					let dup_count = v.len();
+					let (_, _, id_bb_reactant, _, _, _, _, _, _) = v.into_iter().next()?;
+
+					counter_raw_products.fetch_add(dup_count, Ordering::Relaxed);
+					counter_dup_products.fetch_add(dup_count - 1, Ordering::Relaxed);
+					counter_shared_products.fetch_add(1, Ordering::Relaxed);
+					counter_final_products.fetch_add(1, Ordering::Relaxed);
+
+					Some((product_ids[&key], id_bb_reactant, dup_count as i32))
+				})
+				.collect_vec();
+
+			{
+				let mut conn = db_pool.get().unwrap();
+
+				let dup_counts = shared
+					.iter()
+					.map(|(id, _, dup_count)| (*id, *dup_count))
+					.collect_vec();
+
+				// Bind parameters of a statement are limited
+				if let Err(err) = dup_counts
+					.chunks(65535 / 2)
+					.try_for_each(|chunk| db::model::update_experiment_product_dup_counts(&mut conn, chunk)) {
+					eprintln!("Error: Failed to update experiment products duplicate counts: {err:?}");
+				}
+
+				shared
+					.iter()
+					.map(|(id, id_bb_reactant, _)| NewExperimentProductOrigin {
+						id_building_block_reactant: *id_bb_reactant,
+						id_experiment_product: *id,
+					})
+					.collect_vec()
+					.chunks(65535 / NewExperimentProductOrigin::field_count())
+					.for_each(|chunk| {
+						if db::model::create_experiment_product_origins(&mut conn, chunk).is_err() {
+							eprintln!("Error: Failed to insert experiment product origins");
+						}
+					});
+			}
+
+			let inserted = grouped
 				.par_drain()
 				.map(|elem| -> Result<_, &str> {
-					let (smiles, v) = elem;
+					let (key, v) = elem;
 					let dup_count = v.len();
 
-					// Use the first duplicate (TODO: trace the origin of all the duplicates).
-					let (mut product, id_frag_reactant, id_bb_reactant, name, fullname) = v
+					// Use the duplicate whose building block needs the fewest protecting groups (TODO: trace the origin of all the duplicates).
+					let (mut product, id_frag_reactant, id_bb_reactant, name, fullname, route_smiles, smiles, identifiers, incompatible_groups) = v
 						.into_iter()
-						.next()
+						.min_by_key(|(_, _, _, _, _, _, _, _, incompatible_groups)| protecting_group_count(incompatible_groups.as_deref()))
 						.ok_or("No products were generated")?;
 
+					let protecting_groups = protecting_group_count(incompatible_groups.as_deref());
+
 					product.compute_2d_coords();
 
 					let pickle = product.to_pickle(Some(common::DEFAULT_MOL_PICKLE_OPTIONS))
@@ -392,11 +531,42 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 					let mw = product.calc_exact_mw() as f32;
 					let tpsa = product.calc_tpsa() as f32;
 
+					let score = score_profile
+						.as_ref()
+						.map(|profile| profile.score(&score::Descriptors { fsp3, hba, hbd, clogp, mw, tpsa }));
+
+					let scaffolds = common::scaffold::murcko_scaffolds(&product);
+					let prod_ring_systems = common::scaffold::ring_system_smiles(&product);
+					let new_ring_systems = common::scaffold::new_ring_systems(frag_ring_systems, &prod_ring_systems)
+						.join(".");
+
 					counter_raw_products.fetch_add(dup_count, Ordering::Relaxed);
 					counter_dup_products.fetch_add(dup_count - 1, Ordering::Relaxed);
 					counter_final_products.fetch_add(1, Ordering::Relaxed);
 
-					Ok((id_frag_reactant, id_bb_reactant, name, fullname, smiles, pickle, dup_count, fsp3, hba, hbd, clogp, mw, tpsa))
+					Ok(GeneratedProduct {
+						key,
+						id_frag_reactant,
+						id_bb_reactant,
+						name,
+						fullname,
+						smiles,
+						pickle,
+						dup_count,
+						fsp3,
+						hba,
+						hbd,
+						clogp,
+						mw,
+						tpsa,
+						scaffolds,
+						new_ring_systems,
+						score,
+						route_smiles,
+						identifiers,
+						protecting_groups,
+						incompatible_groups,
+					})
 				})
 				.filter_map(|e| e.ok())
 				.collect::<Vec<_>>()
@@ -406,19 +576,28 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 
 					let prods: Vec<_> = e
 						.iter()
-						.map(|(id_frag_reactant, _, name, fullname, smiles, pickle, dup_count, fsp3, hba, hbd, clogp, mw, tpsa)| NewExperimentProduct {
-							id_experiment_frag_reactant: *id_frag_reactant,
-							name: &name,
-							fullname: &fullname,
-							rdpickle: &pickle,
-							smiles: &smiles,
-							dup_count: *dup_count as i32,
-							desc_fsp3: *fsp3,
-							desc_hba: *hba,
-							desc_hbd: *hbd,
-							desc_clogp: *clogp,
-							desc_mw: *mw,
-							desc_tpsa: *tpsa,
+						.map(|prod| NewExperimentProduct {
+							id_experiment_frag_reactant: prod.id_frag_reactant,
+							name: &prod.name,
+							fullname: &prod.fullname,
+							rdpickle: &prod.pickle,
+							smiles: &prod.smiles,
+							dup_count: prod.dup_count as i32,
+							desc_fsp3: prod.fsp3,
+							desc_hba: prod.hba,
+							desc_hbd: prod.hbd,
+							desc_clogp: prod.clogp,
+							desc_mw: prod.mw,
+							desc_tpsa: prod.tpsa,
+							scaffold_smiles: prod.scaffolds.as_ref().map(|e| e.scaffold.as_str()),
+							generic_scaffold_smiles: prod.scaffolds.as_ref().map(|e| e.generic.as_str()),
+							new_ring_systems: Some(&prod.new_ring_systems),
+							score: prod.score,
+							route_smiles: prod.route_smiles.as_deref(),
+							inchi: prod.identifiers.as_ref().map(|e| e.inchi.as_str()),
+							inchikey: prod.identifiers.as_ref().map(|e| e.inchikey.as_str()),
+							protecting_groups: prod.protecting_groups,
+							incompatible_groups: prod.incompatible_groups.as_deref(),
 						})
 						.collect();
 
@@ -426,10 +605,10 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 						.map_err(|_| "Failed to insert experiment products")?;
 
 					let prod_origs: Vec<_> = ent_experiment_products
-						.into_iter()
+						.iter()
 						.zip(e)
-						.map(|(ent_experiment_product, e)| NewExperimentProductOrigin {
-							id_building_block_reactant: e.1,
+						.map(|(ent_experiment_product, prod)| NewExperimentProductOrigin {
+							id_building_block_reactant: prod.id_bb_reactant,
 							id_experiment_product: ent_experiment_product.id,
 						})
 						.collect();
@@ -437,10 +616,26 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 					db::model::create_experiment_product_origins(&mut conn, &prod_origs)
 						.map_err(|_| "Failed to insert experiment product origins")?;
 
-					Ok(())
+					Ok(ent_experiment_products
+						.into_iter()
+						.zip(e)
+						.map(|(ent_experiment_product, prod)| (prod.key.clone(), ent_experiment_product.id))
+						.collect_vec())
+				})
+				.collect::<Vec<_>>();
+
+			inserted
+				.into_iter()
+				.filter_map(|e| {
+					if let Err(err) = &e {
+						eprintln!("Error: {err}");
+					}
+					e.ok()
 				})
-				.filter_map(|e| e.err())
-				.for_each(|err| eprintln!("Error: {err}"));
+				.flatten()
+				.for_each(|(key, id)| {
+					product_ids.insert(key, id);
+				});
 		});
 
 		eprintln!("   completed.");
@@ -457,23 +652,128 @@ pub fn experiment_gen_products(thread_pool: &ThreadPool, db_pool: &db::DBPool, e
 		})
 		.collect();
 
-	gen_files(thread_pool, db_pool, ent_experiment, "raw", "overall", true);
+	result.distinct_products = product_ids.len();
+
+	eprintln!("Checking products against the catalog...");
+
+	catalog::experiment_match_catalog(db_pool, ent_experiment);
+	catalog::experiment_summarize_offers(db_pool, ent_experiment);
+
+	eprintln!(" completed.");
+
+	if let Some(cutoff) = ent_experiment.cluster_cutoff {
+		cluster::experiment_cluster_products(thread_pool, db_pool, ent_experiment, cutoff);
+	}
+
+	gen_files(thread_pool, db_pool, ent_experiment, "raw", "overall", opts);
 
 	*ent_experiment = db::model::update_experiment(&mut conn, ent_experiment.id, &NewExperiment {
 		name: &ent_experiment.name,
 		status: &ent_experiment.status,
 		ts_start: ent_experiment.ts_start,
 		ts_end: Some(chrono::Utc::now().naive_utc()),
+		cluster_cutoff: ent_experiment.cluster_cutoff,
+		score_profile: ent_experiment.score_profile.as_ref(),
+		id_catalog_snapshot: ent_experiment.id_catalog_snapshot,
+		max_price_per_mg: ent_experiment.max_price_per_mg,
+		max_lead_time_days: ent_experiment.max_lead_time_days,
 	}).unwrap();
 
 	result
 }
 
-pub fn gen_files(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, gen_img: bool) {
-	gen_files_filtered(thread_pool, db_pool, ent_experiment, prefix, filename_prefix, gen_img, None);
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+	pub gen_img: bool,
+	/// Individual SVG and PNG depiction of each product, with the fragment and the new bonds highlighted
+	pub gen_product_img: bool,
+	pub cluster_representative: Option<ClusterRepresentative>,
+	/// Provider names by decreasing preference, for building blocks sold by several providers
+	pub provider_preference: Vec<String>,
+	/// Paged image grids covering all the products of each reaction
+	pub grid: Option<GridOptions>,
+}
+
+struct ExportedProduct<'m> {
+	id: i64,
+	mw: f64,
+	score: Option<f32>,
+	mol: InitializedHeap<'m, ROMol>,
+	legend: String,
+	fullname: String,
+	smiles: String,
+	inchikey: Option<String>,
+	sdf: String,
+	tsv: String,
+	route: Option<(String, String)>,
+	route_smiles: Option<String>,
+	id_reaction: i64,
+	cluster_idx: Option<i32>,
+	cluster_centroid: Option<bool>,
+	cluster_similarity: Option<f32>,
+	scaffold_smiles: Option<String>,
+	generic_scaffold_smiles: Option<String>,
+	new_ring_systems: Option<String>,
+	fsp3: f32,
+	hba: i32,
+	hbd: i32,
+	clogp: f32,
+	tpsa: f32,
+}
+
+// Products reached through several reactions list every route, separated by ';', the reaction the product is stored with comes first
+fn merge_provenances(rows: &[ExperimentProductProvenance], id_reaction: i64) -> Option<ExperimentProductProvenance> {
+	let rows = rows
+		.iter()
+		.sorted_by_key(|row| (row.id_reaction != id_reaction, row.id_reaction))
+		.collect_vec();
+	let first = rows.first()?;
+
+	let join = |field: fn(&ExperimentProductProvenance) -> &str| rows
+		.iter()
+		.map(|row| field(row))
+		.join(";");
+
+	Some(ExperimentProductProvenance {
+		id: first.id,
+		id_reaction: first.id_reaction,
+		dup_count: first.dup_count,
+		reaction_name: join(|row| row.reaction_name.as_str()),
+		reaction_slug: join(|row| row.reaction_slug.as_str()),
+		reaction_reference: first.reaction_reference.clone(),
+		bb_smiles: join(|row| row.bb_smiles.as_str()),
+		refids: join(|row| row.refids.as_str()),
+		providers: join(|row| row.providers.as_str()),
+	})
+}
+
+// Best scores first, unscored products last, ties broken by increasing MW
+fn cmp_ranked(score0: Option<f32>, mw0: f64, score1: Option<f32>, mw1: f64) -> std::cmp::Ordering {
+	let by_score = match (score0, score1) {
+		(Some(score0), Some(score1)) => f32::total_cmp(&score1, &score0),
+		(Some(_), None) => std::cmp::Ordering::Less,
+		(None, Some(_)) => std::cmp::Ordering::Greater,
+		(None, None) => std::cmp::Ordering::Equal,
+	};
+
+	by_score.then(f64::total_cmp(&mw0, &mw1))
+}
+
+// Long names would overflow the grid panels
+fn truncate_legend(mut name: String) -> String {
+	if name.graphemes(true).count() > 50 {
+		name.truncate(name.grapheme_indices(true).nth(47).unwrap().0);
+		name.push_str("...");
+	}
+
+	name
+}
+
+pub fn gen_files(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, opts: &ExportOptions) {
+	gen_files_filtered(thread_pool, db_pool, ent_experiment, prefix, filename_prefix, opts, None);
 }
 
-pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, gen_img: bool, ent_experiment_postproc_filter: Option<&ExperimentPostprocFilter>) {
+pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, opts: &ExportOptions, ent_experiment_postproc_filter: Option<&ExperimentPostprocFilter>) {
 	let exp_uuid_str = ent_experiment.uuid.to_string();
 
 	let path_prefix = Path::new(prefix);
@@ -523,13 +823,24 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 					.unwrap();
 
 				bb_mol.set_prop_str("_Name", &ent_building_block.name);
+				if let Some(inchikey) = &ent_building_block.inchikey {
+					bb_mol.set_prop_str("inchikey", inchikey);
+				}
+				if let Some(price_per_mg) = ent_building_block.price_per_mg {
+					bb_mol.set_prop_str("price_per_mg", &price_per_mg.to_string());
+				}
+				if let Some(lead_time_days) = ent_building_block.lead_time_days {
+					bb_mol.set_prop_str("lead_time_days", &lead_time_days.to_string());
+				}
 
 				let mw = bb_mol.calc_exact_mw();
 				let sdf = bb_mol
 					.to_sd()
 					.unwrap();
 
-				(mw, ent_building_block.name, ent_building_block.smiles, sdf)
+				let offer = (ent_building_block.price_per_mg, ent_building_block.lead_time_days);
+
+				(mw, ent_building_block.name, ent_building_block.smiles, sdf, ent_building_block.scaffold_smiles, ent_building_block.inchikey, offer)
 			})
 			.collect();
 
@@ -537,7 +848,7 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 		eprintln!("Sorting building blocks...");
 
-		ent_building_blocks.par_sort_unstable_by(|(mw0, _, _, _), (mw1, _, _, _)|
+		ent_building_blocks.par_sort_unstable_by(|(mw0, _, _, _, _, _, _), (mw1, _, _, _, _, _, _)|
 			f64::total_cmp(mw0, mw1));
 
 		eprintln!(" completed.");
@@ -546,22 +857,63 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 		file_out_zip.start_file(format!("{filename_prefix}_bbs.smi"), zip_opts.clone()).unwrap();
 
-		writeln!(&mut file_out_zip, "Smiles\tName").unwrap();
+		writeln!(&mut file_out_zip, "Smiles\tName\tInChIKey\tPricePerMg\tLeadTimeDays").unwrap();
 		ent_building_blocks
 			.iter()
-			.for_each(|(_, name, smiles, _)| {
-				writeln!(&mut file_out_zip, "{smiles}\t{name}")
+			.for_each(|(_, name, smiles, _, _, inchikey, (price_per_mg, lead_time_days))| {
+				writeln!(&mut file_out_zip, "{smiles}\t{name}\t{}\t{}\t{}",
+					inchikey.as_deref().unwrap_or_default(),
+					price_per_mg.map(|price_per_mg| price_per_mg.to_string()).unwrap_or_default(),
+					lead_time_days.map(|lead_time_days| lead_time_days.to_string()).unwrap_or_default())
 					.expect(&format!("Failed to write building block to SMILES file for experiment {exp_uuid_str}"));
 			});
 
 		file_out_zip.start_file(format!("{filename_prefix}_bbs.sdf"), zip_opts.clone()).unwrap();
 
-		ent_building_blocks
+		let bb_scaffolds = ent_building_blocks
 			.into_iter()
-			.for_each(|(_, _, _, sdf)| {
+			.map(|(_, _, _, sdf, scaffold_smiles, _, _)| {
 				file_out_zip.write_all(sdf.as_bytes())
 					.expect(&format!("Failed to write building block to SDF file for experiment {exp_uuid_str}"));
-			});
+
+				scaffold_smiles
+			})
+			.collect_vec();
+
+		eprintln!(" completed.");
+
+		eprintln!("Writing purchase lists...");
+
+		{
+			let mut conn = db_pool.get().unwrap();
+
+			let it = if let Some(ent) = ent_experiment_postproc_filter {
+				Either::Left(db::model::PurchasableBuildingBlock::get_with_experiment_postproc_filter(&mut conn, &ent).unwrap())
+			} else {
+				Either::Right(db::model::PurchasableBuildingBlock::get_with_experiment(&mut conn, &ent_experiment).unwrap())
+			};
+
+			purchase::gen_purchase_lists(&mut file_out_zip, &zip_opts, filename_prefix, &exp_uuid_str, &opts.provider_preference, it.filter_map(|e| e.ok()));
+		}
+
+		eprintln!(" completed.");
+
+		eprintln!("Fetching products provenance...");
+
+		let provenances: HashMap<_, _> = {
+			let mut conn = db_pool.get().unwrap();
+
+			let it = if let Some(ent) = ent_experiment_postproc_filter {
+				Either::Left(db::model::ExperimentProductProvenance::get_with_experiment_postproc_filter(&mut conn, &ent).unwrap())
+			} else {
+				Either::Right(db::model::ExperimentProductProvenance::get_with_experiment(&mut conn, &ent_experiment).unwrap())
+			};
+
+			it
+				.filter_map(|e| e.ok())
+				.into_group_map_by(|e| e.id)
+		};
+		let provenances = &provenances;
 
 		eprintln!(" completed.");
 
@@ -595,18 +947,136 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 				prod_mol.set_prop_str("_Name", &ent_product.fullname);
 
-				let mw = prod_mol.calc_exact_mw();
-				let sdf = prod_mol.to_sd().unwrap();
+				if let (Some(inchi), Some(inchikey)) = (&ent_product.inchi, &ent_product.inchikey) {
+					prod_mol.set_prop_str("inchi", inchi);
+					prod_mol.set_prop_str("inchikey", inchikey);
+				}
+
+				if let (Some(cluster_idx), Some(cluster_centroid)) = (ent_product.cluster_idx, ent_product.cluster_centroid) {
+					prod_mol.set_prop_i32("cluster", cluster_idx);
+					prod_mol.set_prop_i32("cluster_centroid", cluster_centroid as i32);
+				}
 
-				let mut name = ent_product.name;
+				if let Some(scaffold_smiles) = &ent_product.scaffold_smiles {
+					prod_mol.set_prop_str("scaffold", scaffold_smiles);
+				}
+
+				if let Some(score) = ent_product.score {
+					prod_mol.set_prop_str("score", &format!("{score:.3}"));
+				}
 
-				if name.graphemes(true).count() > 50 {
-					name = name.clone();
-					name.truncate(name.grapheme_indices(true).nth(47).unwrap().0);
-					name.push_str("...");
+				prod_mol.set_prop_i32("purchasable", ent_product.purchasable as i32);
+				if let Some(refids) = &ent_product.purchasable_refids {
+					prod_mol.set_prop_str("purchasable_refids", refids);
 				}
 
-				(mw, prod_mol, name, ent_product.fullname, ent_product.smiles, sdf, ent_product.id_reaction)
+				prod_mol.set_prop_i32("protecting_groups", ent_product.protecting_groups);
+				if let Some(incompatible_groups) = &ent_product.incompatible_groups {
+					prod_mol.set_prop_str("incompatible_groups", incompatible_groups);
+				}
+
+				prod_mol.set_prop_str("fsp3", &format!("{:.3}", ent_product.desc_fsp3));
+				prod_mol.set_prop_i32("hba", ent_product.desc_hba);
+				prod_mol.set_prop_i32("hbd", ent_product.desc_hbd);
+				prod_mol.set_prop_str("clogp", &format!("{:.3}", ent_product.desc_clogp));
+				prod_mol.set_prop_str("mw", &format!("{:.3}", ent_product.desc_mw));
+				prod_mol.set_prop_str("tpsa", &format!("{:.3}", ent_product.desc_tpsa));
+
+				let routes = provenances.get(&ent_product.id);
+				let merged_provenance = routes.and_then(|rows| merge_provenances(rows, ent_product.id_reaction));
+				let provenance = merged_provenance.as_ref();
+				// Route of the reaction the product is stored with
+				let primary_provenance = routes.and_then(|rows| rows.iter().find(|row| row.id_reaction == ent_product.id_reaction));
+
+				if let Some(provenance) = provenance {
+					prod_mol.set_prop_str("reaction", &provenance.reaction_name);
+					prod_mol.set_prop_str("reaction_slug", &provenance.reaction_slug);
+					if let Some(reference) = &provenance.reaction_reference {
+						prod_mol.set_prop_str("reaction_reference", reference);
+					}
+					prod_mol.set_prop_str("bb_smiles", &provenance.bb_smiles);
+					prod_mol.set_prop_str("refids", &provenance.refids);
+					prod_mol.set_prop_str("providers", &provenance.providers);
+					prod_mol.set_prop_i32("dup_count", provenance.dup_count);
+				}
+
+				let mw = prod_mol.calc_exact_mw();
+				let sdf = prod_mol.to_sd().unwrap();
+
+				let tsv = [
+					ent_product.smiles.clone(),
+					ent_product.fullname.clone(),
+					provenance.map(|e| e.reaction_name.clone()).unwrap_or_default(),
+					provenance.map(|e| e.reaction_slug.clone()).unwrap_or_default(),
+					provenance.map(|e| e.bb_smiles.clone()).unwrap_or_default(),
+					provenance.map(|e| e.refids.clone()).unwrap_or_default(),
+					provenance.map(|e| e.providers.clone()).unwrap_or_default(),
+					provenance.map(|e| e.dup_count.to_string()).unwrap_or_default(),
+					format!("{:.3}", ent_product.desc_fsp3),
+					ent_product.desc_hba.to_string(),
+					ent_product.desc_hbd.to_string(),
+					format!("{:.3}", ent_product.desc_clogp),
+					format!("{:.3}", ent_product.desc_mw),
+					format!("{:.3}", ent_product.desc_tpsa),
+					ent_product.cluster_idx.map(|e| e.to_string()).unwrap_or_default(),
+					ent_product.cluster_centroid.map(|e| (e as i32).to_string()).unwrap_or_default(),
+					ent_product.scaffold_smiles.clone().unwrap_or_default(),
+					ent_product.generic_scaffold_smiles.clone().unwrap_or_default(),
+					ent_product.new_ring_systems.clone().unwrap_or_default(),
+					ent_product.score.map(|e| format!("{e:.3}")).unwrap_or_default(),
+					(ent_product.purchasable as i32).to_string(),
+					ent_product.purchasable_refids.clone().unwrap_or_default(),
+					ent_product.inchi.clone().unwrap_or_default(),
+					ent_product.inchikey.clone().unwrap_or_default(),
+					ent_product.protecting_groups.to_string(),
+					ent_product.incompatible_groups.clone().unwrap_or_default(),
+				].join("\t");
+
+				// Mapped reaction SMILES line and RDF entry
+				let route = ent_product.route_smiles
+					.as_deref()
+					.and_then(|route_smiles| {
+						let reaction_name = primary_provenance.map(|e| e.reaction_name.as_str()).unwrap_or_default();
+						let reaction_reference = primary_provenance.and_then(|e| e.reaction_reference.as_deref()).unwrap_or_default();
+
+						let rdf = route::rdf_entry(route_smiles, &ent_product.fullname, &[
+							("NAME", &ent_product.fullname),
+							("INCHIKEY", ent_product.inchikey.as_deref().unwrap_or_default()),
+							("REACTION", reaction_name),
+							("REFERENCE", reaction_reference),
+						])?;
+
+						Some((format!("{route_smiles}\t{}\t{}\t{reaction_name}\t{reaction_reference}", ent_product.fullname, ent_product.inchikey.as_deref().unwrap_or_default()), rdf))
+					});
+
+				let name = truncate_legend(ent_product.name);
+
+				ExportedProduct {
+					id: ent_product.id,
+					mw,
+					score: ent_product.score,
+					mol: prod_mol,
+					legend: name,
+					fullname: ent_product.fullname,
+					smiles: ent_product.smiles,
+					inchikey: ent_product.inchikey,
+					sdf,
+					tsv,
+					route,
+					route_smiles: ent_product.route_smiles,
+					id_reaction: ent_product.id_reaction,
+					cluster_idx: ent_product.cluster_idx,
+					cluster_centroid: ent_product.cluster_centroid,
+					cluster_similarity: ent_product.cluster_similarity,
+					scaffold_smiles: ent_product.scaffold_smiles,
+					generic_scaffold_smiles: ent_product.generic_scaffold_smiles,
+					new_ring_systems: ent_product.new_ring_systems,
+					fsp3: ent_product.desc_fsp3,
+					hba: ent_product.desc_hba,
+					hbd: ent_product.desc_hbd,
+					clogp: ent_product.desc_clogp,
+					tpsa: ent_product.desc_tpsa,
+				}
 			})
 		.collect();
 
@@ -614,14 +1084,53 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 		eprintln!("Sorting products...");
 
-		ent_products.par_sort_unstable_by(|(mw0, _, _, _, _, _, _), (mw1, _, _, _, _, _, _)|
-			f64::total_cmp(mw0, mw1));
+		ent_products.par_sort_unstable_by(|prod0, prod1|
+			cmp_ranked(prod0.score, prod0.mw, prod1.score, prod1.mw));
 
 		eprintln!(" completed.");
 
+		if let Some(representative) = opts.cluster_representative {
+			eprintln!("Selecting cluster representatives...");
+
+			let clustered = ent_products
+				.iter()
+				.filter_map(|prod| prod.cluster_idx.map(|cluster_idx| (cluster_idx, prod)));
+
+			let representatives: HashSet<i64> = match representative {
+				// The centroid may have been filtered out, the most similar remaining member stands for it
+				ClusterRepresentative::Centroid => clustered
+					.into_grouping_map()
+					.max_by(|_, prod0, prod1| (prod0.cluster_centroid == Some(true)).cmp(&(prod1.cluster_centroid == Some(true)))
+						.then_with(|| f32::total_cmp(&prod0.cluster_similarity.unwrap_or(f32::NEG_INFINITY), &prod1.cluster_similarity.unwrap_or(f32::NEG_INFINITY))))
+					.into_values()
+					.map(|prod| prod.id)
+					.collect(),
+				ClusterRepresentative::LowestMw => clustered
+					.into_grouping_map()
+					.min_by(|_, prod0, prod1| f64::total_cmp(&prod0.mw, &prod1.mw))
+					.into_values()
+					.map(|prod| prod.id)
+					.collect(),
+				ClusterRepresentative::BestScore => clustered
+					.into_grouping_map()
+					.min_by(|_, prod0, prod1| cmp_ranked(prod0.score, prod0.mw, prod1.score, prod1.mw))
+					.into_values()
+					.map(|prod| prod.id)
+					.collect(),
+			};
+
+			ent_products.retain(|prod| prod.cluster_idx.is_none() || representatives.contains(&prod.id));
+
+			eprintln!(" completed.");
+		}
+
+		if let Some(top_n) = ent_experiment_postproc_filter.and_then(|ent| ent.top_n) {
+			ent_products.truncate(top_n.max(0) as usize);
+		}
+
 		eprintln!("Drawing products subset images...");
 
-		if gen_img {
+		if opts.gen_img {
 			let mut conn = db_pool.get().unwrap();
 
 			let ent_reactions = db::model::get_reactions_with_experiment(&mut conn, &ent_experiment)
@@ -632,9 +1141,9 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 					let (mols, legends): (Vec<_>, Vec<_>) = ent_products
 						.iter()
-						.filter_map(|(_, prod_mol, name, _, _, _, id_reaction)|
-							(*id_reaction == ent_reaction.id)
-								.then_some((prod_mol, name.as_str())))
+						.filter_map(|prod|
+							(prod.id_reaction == ent_reaction.id)
+								.then_some((&prod.mol, prod.legend.as_str())))
 						.take(num_products)
 						.unzip();
 
@@ -688,15 +1197,66 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 			eprintln!(" skipped.");
 		}
 
+		eprintln!("Drawing products pages...");
+
+		if let Some(grid_opts) = opts.grid.as_ref() {
+			let mut conn = db_pool.get().unwrap();
+
+			let ent_reactions = db::model::get_reactions_with_experiment(&mut conn, &ent_experiment)
+				.unwrap()
+				.filter_map(|e| e.ok())
+				.collect_vec();
+
+			grid::gen_grid_pages(path_prefix, &exp_uuid_str, grid_opts, &ent_reactions, &ent_products);
+
+			eprintln!(" completed.");
+		} else {
+			eprintln!(" skipped.");
+		}
+
+		eprintln!("Summarizing scaffolds...");
+
+		{
+			let mut conn = db_pool.get().unwrap();
+
+			let ent_reactions = db::model::get_reactions_with_experiment(&mut conn, &ent_experiment)
+				.unwrap()
+				.filter_map(|e| e.ok())
+				.collect_vec();
+
+			let frag_ring_systems = db::model::get_experiment_frags_with_experiment(&mut conn, &ent_experiment)
+				.unwrap()
+				.first()
+				.map(|ent_frag| {
+					let frag_mol = new_local!(ROMol);
+					let frag_mol = frag_mol
+						.init(ROMolFromPickleParams {
+							pickle: &ent_frag.rdpickle
+						})
+						.unwrap();
+
+					common::scaffold::ring_system_smiles(&frag_mol)
+				})
+				.unwrap_or_default();
+
+			scaffold::gen_scaffold_summary(path_prefix, &mut file_out_zip, &zip_opts, filename_prefix, &exp_uuid_str, opts.gen_img, &ent_reactions, &frag_ring_systems, &ent_products, &bb_scaffolds);
+		}
+
+		eprintln!(" completed.");
+
 		eprintln!("Writing products...");
 
 		file_out_zip.start_file(format!("{filename_prefix}_products.smi"), zip_opts.clone()).unwrap();
 
-		writeln!(&mut file_out_zip, "Smiles\tName").unwrap();
+		writeln!(&mut file_out_zip, "Smiles\tName\tCluster\tCentroid\tScore\tInChIKey").unwrap();
 		ent_products
 			.iter()
-			.for_each(|(_, _, _, fullname, smiles, sdf, _)| {
-				writeln!(&mut file_out_zip, "{smiles}\t{fullname}")
+			.for_each(|prod| {
+				let cluster_idx = prod.cluster_idx.map(|e| e.to_string()).unwrap_or_default();
+				let cluster_centroid = prod.cluster_centroid.map(|e| (e as i32).to_string()).unwrap_or_default();
+				let score = prod.score.map(|e| format!("{e:.3}")).unwrap_or_default();
+
+				writeln!(&mut file_out_zip, "{}\t{}\t{cluster_idx}\t{cluster_centroid}\t{score}\t{}", prod.smiles, prod.fullname, prod.inchikey.as_deref().unwrap_or_default())
 					.expect(&format!("Failed to write product to SMILES file for experiment {exp_uuid_str}"));
 			});
 
@@ -704,11 +1264,85 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 
 		ent_products
 			.iter()
-			.for_each(|(_, _, _, fullname, smiles, sdf, _)| {
-				file_out_zip.write_all(sdf.as_bytes())
+			.for_each(|prod| {
+				file_out_zip.write_all(prod.sdf.as_bytes())
 					.expect(&format!("Failed to write product to SDF file for experiment {exp_uuid_str}"));
 			});
 
+		file_out_zip.start_file(format!("{filename_prefix}_products.tsv"), zip_opts.clone()).unwrap();
+
+		writeln!(&mut file_out_zip, "Smiles\tName\tReaction\tReactionSlug\tBuildingBlockSmiles\tRefids\tProviders\tDupCount\tFsp3\tHBA\tHBD\tCLogP\tMW\tTPSA\tCluster\tCentroid\tScaffold\tGenericScaffold\tNewRingSystems\tScore\tPurchasable\tPurchasableRefids\tInChI\tInChIKey\tProtectingGroups\tIncompatibleGroups").unwrap();
+		ent_products
+			.iter()
+			.for_each(|prod| {
+				writeln!(&mut file_out_zip, "{}", prod.tsv)
+					.expect(&format!("Failed to write product to TSV file for experiment {exp_uuid_str}"));
+			});
+
+		if opts.gen_product_img {
+			eprintln!("Drawing product images...");
+
+			// Fragment and building block are told apart in the route by the component count of the fragment
+			let frag_components = {
+				let mut conn = db_pool.get().unwrap();
+
+				db::model::get_experiment_frags_with_experiment(&mut conn, &ent_experiment)
+					.unwrap()
+					.first()
+					.map_or(1, |ent_frag| ent_frag.smiles.split('.').count())
+			};
+
+			// Images are drawn and written by chunks to bound memory usage
+			ent_products
+				.iter()
+				.filter_map(|prod| prod.route_smiles.as_deref().map(|route_smiles| (prod.id, route_smiles, prod.legend.as_str())))
+				.chunks(1024)
+				.into_iter()
+				.for_each(|chunk| {
+					let depictions = chunk
+						.collect_vec()
+						.into_par_iter()
+						.filter_map(|(id, route_smiles, legend)| depict::depict_product(route_smiles, frag_components, legend).map(|e| (id, e)))
+						.collect::<Vec<_>>();
+
+					depictions
+						.into_iter()
+						.for_each(|(id, depiction)| {
+							file_out_zip.start_file(format!("{filename_prefix}_images/product{id}.svg"), zip_opts.clone()).unwrap();
+							file_out_zip.write_all(depiction.svg.as_bytes())
+								.expect(&format!("Failed to write product {id} SVG image for experiment {exp_uuid_str}"));
+
+							file_out_zip.start_file(format!("{filename_prefix}_images/product{id}.png"), zip_opts.clone()).unwrap();
+							file_out_zip.write_all(&depiction.png)
+								.expect(&format!("Failed to write product {id} PNG image for experiment {exp_uuid_str}"));
+						});
+				});
+
+			eprintln!(" completed.");
+		}
+
+		file_out_zip.start_file(format!("{filename_prefix}_routes.smi"), zip_opts.clone()).unwrap();
+
+		writeln!(&mut file_out_zip, "ReactionSmiles\tName\tInChIKey\tReaction\tReference").unwrap();
+		ent_products
+			.iter()
+			.filter_map(|prod| prod.route.as_ref())
+			.for_each(|(line, _)| {
+				writeln!(&mut file_out_zip, "{line}")
+					.expect(&format!("Failed to write route to reaction SMILES file for experiment {exp_uuid_str}"));
+			});
+
+		file_out_zip.start_file(format!("{filename_prefix}_routes.rdf"), zip_opts.clone()).unwrap();
+
+		file_out_zip.write_all(route::rdf_header().as_bytes()).unwrap();
+		ent_products
+			.iter()
+			.filter_map(|prod| prod.route.as_ref())
+			.for_each(|(_, rdf)| {
+				file_out_zip.write_all(rdf.as_bytes())
+					.expect(&format!("Failed to write route to RDF file for experiment {exp_uuid_str}"));
+			});
+
 		file_out_zip
 			.finish()
 			.unwrap();
@@ -717,7 +1351,22 @@ pub fn gen_files_filtered(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_ex
 	});
 }
 
-pub fn gen_files_filtered_3d(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, ent_experiment_postproc_filter: &ExperimentPostprocFilter) {
+#[derive(Clone, Debug, Default)]
+pub struct Export3DOptions {
+	/// Write one PDBQT file per ligand instead of a single multi-model file
+	pub pdbqt_split: bool,
+}
+
+struct Exported3DProduct {
+	id: i64,
+	mw: f64,
+	score: Option<f32>,
+	sdf: String,
+	mol2: String,
+	pdbqt: Option<String>,
+}
+
+pub fn gen_files_filtered_3d(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent_experiment: &Experiment, prefix: &str, filename_prefix: &str, opts: &Export3DOptions, ent_experiment_postproc_filter: &ExperimentPostprocFilter) {
 	let mut conn = db_pool.get().unwrap();
 
 	let exp_uuid_str = ent_experiment.uuid.to_string();
@@ -741,9 +1390,20 @@ pub fn gen_files_filtered_3d(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent
 		let (tx, rx) = mpmc();
 
 		scope.spawn(move |_| {
-			db::model::ExportableExperimentProduct::get_with_experiment_postproc_filter(&mut conn, &ent_experiment_postproc_filter)
+			let mut ent_products = db::model::ExportableExperimentProduct::get_with_experiment_postproc_filter(&mut conn, &ent_experiment_postproc_filter)
 				.unwrap()
 				.filter_map(|e| e.ok())
+				.collect_vec();
+
+			// Rank before embedding so that only the kept products are embedded
+			if let Some(top_n) = ent_experiment_postproc_filter.top_n {
+				ent_products.par_sort_unstable_by(|prod0, prod1|
+					cmp_ranked(prod0.score, prod0.desc_mw as f64, prod1.score, prod1.desc_mw as f64));
+				ent_products.truncate(top_n.max(0) as usize);
+			}
+
+			ent_products
+				.into_iter()
 				.for_each(|e| {
 					tx.send(e).unwrap();
 				});
@@ -761,14 +1421,33 @@ pub fn gen_files_filtered_3d(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent
 					.unwrap();
 
 				prod_mol.set_prop_str("_Name", &ent_product.fullname);
+				if let Some(inchikey) = &ent_product.inchikey {
+					prod_mol.set_prop_str("inchikey", inchikey);
+				}
 				prod_mol.embed_molecule()
 					.map_err(|_| format!("Failed to embed product '{}'", ent_product.fullname))?;
 
+				if let Some(score) = ent_product.score {
+					prod_mol.set_prop_str("score", &format!("{score:.3}"));
+				}
+
 				let mw = prod_mol.calc_exact_mw();
 				let sdf = prod_mol.to_sd().unwrap();
 				let mol2 = prod_mol.to_mol2().unwrap();
+
+				// A ligand that cannot be prepared for docking is still exported in the other formats
+				let pdbqt = pdbqt::to_pdbqt(&prod_mol, &ent_product.fullname)
+					.map_err(|err| eprintln!("{err}"))
+					.ok();
 	
-				Ok((mw, sdf, mol2))
+				Ok(Exported3DProduct {
+					id: ent_product.id,
+					mw,
+					score: ent_product.score,
+					sdf,
+					mol2,
+					pdbqt,
+				})
 			})
 			.filter_map(|e| {
 				if let Err(err) = &e {
@@ -778,25 +1457,46 @@ pub fn gen_files_filtered_3d(thread_pool: &ThreadPool, db_pool: &db::DBPool, ent
 			})
 			.collect();
 	
-		ent_products.par_sort_unstable_by(|(mw0, _, _), (mw1, _, _)|
-			f64::total_cmp(mw0, mw1));
+		ent_products.par_sort_unstable_by(|prod0, prod1|
+			cmp_ranked(prod0.score, prod0.mw, prod1.score, prod1.mw));
 
 		file_out_zip.start_file(format!("{filename_prefix}_products_3d.sdf"), zip_opts.clone()).unwrap();
 		ent_products
 			.iter()
-			.for_each(|(_, sdf, _)| {
-				file_out_zip.write_all(sdf.as_bytes())
+			.for_each(|prod| {
+				file_out_zip.write_all(prod.sdf.as_bytes())
 					.expect(&format!("Failed to write product to SDF file for experiment {exp_uuid_str}"));
 			});
 
 		file_out_zip.start_file(format!("{filename_prefix}_products_3d.mol2"), zip_opts.clone()).unwrap();
 		ent_products
-			.into_iter()
-			.for_each(|(_, _, mol2)| {
-				file_out_zip.write_all(mol2.as_bytes())
+			.iter()
+			.for_each(|prod| {
+				file_out_zip.write_all(prod.mol2.as_bytes())
 					.expect(&format!("Failed to write product to MOL2 file for experiment {exp_uuid_str}"));
 			});
 
+		if opts.pdbqt_split {
+			ent_products
+				.iter()
+				.filter_map(|prod| prod.pdbqt.as_ref().map(|pdbqt| (prod.id, pdbqt)))
+				.for_each(|(id, pdbqt)| {
+					file_out_zip.start_file(format!("{filename_prefix}_products_pdbqt/product{id}.pdbqt"), zip_opts.clone()).unwrap();
+					file_out_zip.write_all(pdbqt.as_bytes())
+						.expect(&format!("Failed to write product {id} to PDBQT file for experiment {exp_uuid_str}"));
+				});
+		} else {
+			file_out_zip.start_file(format!("{filename_prefix}_products_3d.pdbqt"), zip_opts.clone()).unwrap();
+			ent_products
+				.iter()
+				.filter_map(|prod| prod.pdbqt.as_ref())
+				.enumerate()
+				.for_each(|(idx, pdbqt)| {
+					write!(&mut file_out_zip, "MODEL {}\n{pdbqt}ENDMDL\n", idx + 1)
+						.expect(&format!("Failed to write product to PDBQT file for experiment {exp_uuid_str}"));
+				});
+		}
+
 		file_out_zip.finish().unwrap();
 	});
 }
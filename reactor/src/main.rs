@@ -4,6 +4,8 @@ use chemodots_db as db;
 use chemodots_reactor as reactor;
 use itertools::Itertools;
 use reactor::ReactionCounter;
+use reactor::grid::{GridOptions, GridSortKey};
+use reactor::score::{ScoreProfile, ScoreProfileQuery};
 use serde_json::{json, Value};
 
 fn format_duration_hh_mm_ss(d: &chrono::Duration) -> String {
@@ -37,15 +39,38 @@ fn main() {
 	let atoms = v["atoms"].as_array().unwrap().into_iter().map(|v| v.as_u64().unwrap() as i32).collect_vec();
 	let rules = v["rules"].as_array().unwrap().into_iter().map(|v| v.as_u64().unwrap() as i64).collect_vec();
 	let bb_dbs = v["bb_dbs"].as_array().unwrap().into_iter().map(|v| v.as_str().unwrap()).collect_vec();
+	let cluster_cutoff = v["cluster_cutoff"].as_f64().map(|v| v as f32);
+	let score_profile = (!v["score_profile"].is_null())
+		.then(|| ScoreProfile::from(serde_json::from_value::<ScoreProfileQuery>(v["score_profile"].clone()).expect("Invalid score profile")));
+	let catalog_snapshot = v["catalog_snapshot"].as_i64();
+	let max_price_per_mg = v["max_price_per_mg"].as_f64().map(|v| v as f32);
+	let max_lead_time_days = v["max_lead_time_days"].as_i64().map(|v| v as i32);
 
-	let mut ent_experiment = reactor::experiment_create(&db_pool, name, smiles, Some(mol), &atoms, &rules, &bb_dbs);
+	let mut ent_experiment = reactor::experiment_create(&db_pool, name, smiles, Some(mol), &atoms, &rules, &bb_dbs, cluster_cutoff, score_profile.as_ref(), catalog_snapshot, max_price_per_mg, max_lead_time_days);
 
 	let exp_uuid_str = ent_experiment.uuid.to_string();
 
 	std::fs::create_dir(&exp_uuid_str).unwrap();
 	std::env::set_current_dir(&exp_uuid_str).unwrap();
 
-	let result = reactor::experiment_gen_products(&thread_pool, &db_pool, &mut ent_experiment);
+	let opts = reactor::ExportOptions {
+		gen_img: true,
+		gen_product_img: v["product_images"].as_bool().unwrap_or(false),
+		provider_preference: v["provider_preference"]
+			.as_array()
+			.map(|v| v.into_iter().map(|v| v.as_str().unwrap().to_owned()).collect_vec())
+			.unwrap_or_else(|| bb_dbs.iter().map(|e| e.to_string()).collect_vec()),
+		grid: Some(GridOptions {
+			page_size: v["grid_page_size"].as_u64().map_or(GridOptions::default().page_size, |v| v as usize),
+			sort: (!v["grid_sort"].is_null())
+				.then(|| serde_json::from_value::<GridSortKey>(v["grid_sort"].clone()).expect("Invalid grid sort key"))
+				.unwrap_or_default(),
+			..Default::default()
+		}),
+		..Default::default()
+	};
+
+	let result = reactor::experiment_gen_products(&thread_pool, &db_pool, &mut ent_experiment, &opts);
 	reactor::plot::gen_plots(&db_pool, &ent_experiment);
 
 	let total_bb_count = db::model::count_building_blocks_with_experiment_providers(&mut db_pool.get().unwrap(), &ent_experiment).unwrap();
@@ -57,9 +82,16 @@ fn main() {
 			reacted_building_blocks: x.reacted_building_blocks + y.reacted_building_blocks, // TODO: Fetch from db
 			raw_products: x.raw_products + y.raw_products,
 			dup_products: x.dup_products + y.dup_products,
+			shared_products: x.shared_products + y.shared_products,
 			undesired_products: x.undesired_products + y.undesired_products,
 			final_products: x.final_products + y.final_products,
 		})
+		.map(|counter| ReactionCounter {
+			// Products generated by several reactions are counted once
+			dup_products: counter.dup_products + counter.shared_products,
+			final_products: result.distinct_products,
+			..counter
+		})
 		.unwrap_or(ReactionCounter::default());
 
 	let mut reactions_json = Vec::new();
@@ -71,6 +103,7 @@ fn main() {
 		"reacted_bb_cnt": overall.reacted_building_blocks,
 		"generated_prod_cnt": overall.raw_products,
 		"duplicate_prod_cnt": overall.dup_products,
+		"shared_prod_cnt": overall.shared_products,
 		"undesired_prod_cnt": overall.undesired_products,
 		"final_prod_cnt": overall.final_products,
 	}));
@@ -86,6 +119,7 @@ fn main() {
 				"reacted_bb_cnt": r.counter.reacted_building_blocks,
 				"generated_prod_cnt": r.counter.raw_products,
 				"duplicate_prod_cnt": r.counter.dup_products,
+				"shared_prod_cnt": r.counter.shared_products,
 				"undesired_prod_cnt": r.counter.undesired_products,
 				"final_prod_cnt": r.counter.final_products,
 			}))
@@ -94,6 +128,7 @@ fn main() {
 	let duration = ent_experiment.ts_end.unwrap() - ent_experiment.ts_start;
 	let res_json = json!({
 		"duration": format_duration_hh_mm_ss(&duration),
+		"catalog_snapshot": ent_experiment.id_catalog_snapshot,
 		"reactions": reactions_json,
 	});
 
@@ -103,6 +138,14 @@ fn main() {
 	file.write_all(res_json.to_string().as_bytes())
 		.map_err(|_| format!("Failed to write json data for experiment {exp_uuid_str}")).unwrap();
 
+	eprintln!("Writing report...");
+
+	if let Err(err) = reactor::report::gen_report(&db_pool, &ent_experiment) {
+		eprintln!(" {err}");
+	}
+
+	eprintln!(" completed.");
+
 	let res_json = json!({
 		"uuid": exp_uuid_str,
 	});
@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use rdkit_rust::*;
+use rdkit_rust::general::props::prelude::*;
+use rdkit_rust::graphmol::bond::BondType;
+use rdkit_rust::graphmol::molops::prelude::*;
+use rdkit_rust::graphmol::partialcharges::GasteigerChargesImpl;
+use rdkit_rust::graphmol::ringinfo::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::graphmol::rwmol::*;
+use rdkit_rust::prelude::*;
+
+const PROP_GASTEIGER_CHARGE: &str = "_GasteigerCharge";
+
+struct PdbqtAtom {
+	idx: u32,
+	name: String,
+	pos: (f64, f64, f64),
+	charge: f64,
+	ad_type: &'static str,
+}
+
+// AutoDock 4 atom type, polar hydrogens are the only hydrogens written
+// None for the elements AutoDock has no parameters for (B, Si, Sn, ...)
+fn ad_type(mol: &impl ROMolImplRef, idx: u32) -> Option<&'static str> {
+	let atom = mol.get_atom(idx).unwrap();
+
+	let ad_type = match atom.get_atomic_num() {
+		1 => "HD",
+		6 if atom.get_is_aromatic() => "A",
+		6 => "C",
+		// Nitrogens without hydrogen and with a free lone pair accept hydrogen bonds
+		7 if atom.get_total_num_hs() == 0 && atom.get_formal_charge() <= 0 && (atom.get_is_aromatic() || atom.get_degree() < 3) => "NA",
+		7 => "N",
+		8 => "OA",
+		9 => "F",
+		15 => "P",
+		16 => "SA",
+		17 => "Cl",
+		35 => "Br",
+		53 => "I",
+		_ => return None,
+	};
+
+	Some(ad_type)
+}
+
+fn is_polar_h(mol: &impl ROMolImplRef, idx: u32) -> bool {
+	mol.get_atom(idx).unwrap().get_atomic_num() == 1
+		&& mol
+			.get_atom_neighbors(idx)
+			.into_iter()
+			.any(|idx_nbr| matches!(mol.get_atom(idx_nbr).unwrap().get_atomic_num(), 7 | 8 | 16))
+}
+
+fn is_heavy(mol: &impl ROMolImplRef, idx: u32) -> bool {
+	mol.get_atom(idx).unwrap().get_atomic_num() != 1
+}
+
+fn heavy_degree(mol: &impl ROMolImplRef, idx: u32) -> usize {
+	mol.get_atom_neighbors(idx)
+		.into_iter()
+		.filter(|idx_nbr| is_heavy(mol, *idx_nbr))
+		.count()
+}
+
+// C(=O)-N bonds are kept rigid, as done by AutoDockTools
+fn is_amide(mol: &impl ROMolImplRef, idx_begin: u32, idx_end: u32) -> bool {
+	let is_carbonyl_c = |idx: u32| mol.get_atom(idx).unwrap().get_atomic_num() == 6
+		&& mol
+			.get_atom_neighbors(idx)
+			.into_iter()
+			.any(|idx_nbr| mol.get_atom(idx_nbr).unwrap().get_atomic_num() == 8
+				&& mol.get_bond_between_atoms(idx, idx_nbr).map(|bond| bond.get_bond_type()) == Some(BondType::DOUBLE));
+	let is_n = |idx: u32| mol.get_atom(idx).unwrap().get_atomic_num() == 7;
+
+	(is_carbonyl_c(idx_begin) && is_n(idx_end)) || (is_n(idx_begin) && is_carbonyl_c(idx_end))
+}
+
+fn rotatable_bonds(mol: &impl ROMolImplRef) -> Vec<(u32, u32)> {
+	let ring_info = mol.get_ring_info();
+
+	(0..mol.get_num_bonds())
+		.filter_map(|idx_bond| {
+			let bond = mol.get_bond(idx_bond).unwrap();
+			let (idx_begin, idx_end) = (bond.get_begin_atom_idx(), bond.get_end_atom_idx());
+
+			(bond.get_bond_type() == BondType::SINGLE
+				&& ring_info.num_bond_rings(idx_bond) == 0
+				&& is_heavy(mol, idx_begin) && is_heavy(mol, idx_end)
+				&& heavy_degree(mol, idx_begin) > 1 && heavy_degree(mol, idx_end) > 1
+				&& !is_amide(mol, idx_begin, idx_end))
+				.then_some((idx_begin, idx_end))
+		})
+		.collect()
+}
+
+struct Branch {
+	// (parent atom, child atom) of the rotatable bond leading to this fragment, None for the root
+	bond: Option<(u32, u32)>,
+	atoms: Vec<u32>,
+	children: Vec<Branch>,
+}
+
+// Written atoms reachable from `idx_start` without crossing a rotatable bond
+fn rigid_fragment(mol: &impl ROMolImplRef, written: &HashSet<u32>, rotatable: &HashSet<(u32, u32)>, idx_start: u32) -> Vec<u32> {
+	let mut atoms = vec![idx_start];
+	let mut stack = vec![idx_start];
+
+	while let Some(idx) = stack.pop() {
+		for idx_nbr in mol.get_atom_neighbors(idx) {
+			if !written.contains(&idx_nbr) || atoms.contains(&idx_nbr)
+				|| rotatable.contains(&(idx, idx_nbr)) || rotatable.contains(&(idx_nbr, idx)) {
+				continue
+			}
+
+			atoms.push(idx_nbr);
+			stack.push(idx_nbr);
+		}
+	}
+
+	atoms.sort();
+	atoms
+}
+
+fn build_branch(mol: &impl ROMolImplRef, written: &HashSet<u32>, rotatable: &HashSet<(u32, u32)>, visited: &mut HashSet<u32>, bond: Option<(u32, u32)>, idx_start: u32) -> Branch {
+	let atoms = rigid_fragment(mol, written, rotatable, idx_start);
+	visited.extend(atoms.iter().copied());
+
+	let mut children = Vec::new();
+
+	for idx in atoms.clone() {
+		for idx_nbr in mol.get_atom_neighbors(idx) {
+			if visited.contains(&idx_nbr) || !(rotatable.contains(&(idx, idx_nbr)) || rotatable.contains(&(idx_nbr, idx))) {
+				continue
+			}
+
+			children.push(build_branch(mol, written, rotatable, visited, Some((idx, idx_nbr)), idx_nbr));
+		}
+	}
+
+	Branch { bond, atoms, children }
+}
+
+fn write_branch(branch: &Branch, atoms: &[PdbqtAtom], serials: &mut Vec<(u32, usize)>, out: &mut String) {
+	let serial_of = |serials: &Vec<(u32, usize)>, idx: u32| serials
+		.iter()
+		.find(|(idx_atom, _)| *idx_atom == idx)
+		.map(|(_, serial)| *serial)
+		.unwrap_or(0);
+
+	match branch.bond {
+		Some((idx_parent, _)) => {
+			// The child atom serial is the next one to be written
+			let serial_parent = serial_of(serials, idx_parent);
+			out.push_str(&format!("BRANCH {:>3} {:>3}\n", serial_parent, serials.len() + 1));
+		},
+		None => out.push_str("ROOT\n"),
+	}
+
+	// The atom bonded to the parent fragment comes first
+	let ordered = branch.atoms
+		.iter()
+		.copied()
+		.sorted_by_key(|idx| Some(*idx) != branch.bond.map(|(_, idx_child)| idx_child));
+
+	for idx in ordered {
+		let atom = atoms.iter().find(|atom| atom.idx == idx).unwrap();
+		let serial = serials.len() + 1;
+		serials.push((idx, serial));
+
+		out.push_str(&format!("ATOM  {:>5} {:<4} UNL     1    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}    {:>6.3} {:<2}\n",
+			serial, atom.name, atom.pos.0, atom.pos.1, atom.pos.2, 1.0, 0.0, atom.charge, atom.ad_type));
+	}
+
+	if branch.bond.is_none() {
+		out.push_str("ENDROOT\n");
+	}
+
+	for child in &branch.children {
+		write_branch(child, atoms, serials, out);
+	}
+
+	if let Some((idx_parent, idx_child)) = branch.bond {
+		out.push_str(&format!("ENDBRANCH {:>3} {:>3}\n", serial_of(serials, idx_parent), serial_of(serials, idx_child)));
+	}
+}
+
+/// PDBQT ligand with Gasteiger charges, merged non-polar hydrogens and a torsion tree rooted at the largest rigid fragment.
+/// `mol` must hold a 3D conformer, hydrogens are added with coordinates when missing.
+pub fn to_pdbqt(mol: &impl ROMolImplRef, name: &str) -> Result<String, String> {
+	let mut mol = RWMol::new(RWMolInitParamsROMol {
+			romol: mol.get_ref(),
+		})
+		.map_err(|_| format!("Failed to copy ligand '{name}'"))?;
+
+	mol.add_hs(&AddHsParameters {
+			add_coords: true,
+			..Default::default()
+		})
+		.map_err(|_| format!("Failed to add hydrogens to ligand '{name}'"))?;
+	mol.compute_gasteiger_charges()
+		.map_err(|_| format!("Failed to compute Gasteiger charges of ligand '{name}'"))?;
+
+	let conformer = mol.get_conformer()
+		.map_err(|_| format!("Ligand '{name}' has no conformer"))?;
+
+	let atom_count = mol.get_num_atoms();
+
+	let charge = |idx: u32| -> f64 {
+		mol.get_atom(idx).unwrap()
+			.get_prop_f64(PROP_GASTEIGER_CHARGE)
+			.ok()
+			.filter(|charge| charge.is_finite())
+			.unwrap_or(0.0)
+	};
+
+	let written: HashSet<u32> = (0..atom_count)
+		.filter(|idx| is_heavy(&mol, *idx) || is_polar_h(&mol, *idx))
+		.collect();
+
+	let atoms = written
+		.iter()
+		.sorted()
+		.enumerate()
+		.map(|(idx_written, idx)| {
+			// Non-polar hydrogens are merged into their heavy atom
+			let merged_charge: f64 = mol
+				.get_atom_neighbors(*idx)
+				.into_iter()
+				.filter(|idx_nbr| !written.contains(idx_nbr))
+				.map(charge)
+				.sum();
+
+			let symbol = mol.get_atom(*idx).unwrap().get_symbol();
+			let ad_type = ad_type(&mol, *idx)
+				.ok_or_else(|| format!("Ligand '{name}' has an atom without AutoDock type ({symbol})"))?;
+			let mut atom_name = format!("{symbol}{}", idx_written + 1);
+			atom_name.truncate(4);
+
+			Ok(PdbqtAtom {
+				idx: *idx,
+				name: atom_name,
+				pos: conformer.get_atom_pos(*idx),
+				charge: charge(*idx) + merged_charge,
+				ad_type,
+			})
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+
+	let rotatable: HashSet<_> = rotatable_bonds(&mol)
+		.into_iter()
+		.collect();
+
+	// Root at the largest rigid fragment
+	let idx_root = written
+		.iter()
+		.copied()
+		.max_by_key(|idx| (rigid_fragment(&mol, &written, &rotatable, *idx).len(), std::cmp::Reverse(*idx)))
+		.ok_or_else(|| format!("Ligand '{name}' has no atoms"))?;
+
+	let mut visited = HashSet::new();
+	let root = build_branch(&mol, &written, &rotatable, &mut visited, None, idx_root);
+
+	let mut out = format!("REMARK  Name = {name}\nREMARK  {} active torsions\n", rotatable.len());
+
+	let mut serials = Vec::new();
+	write_branch(&root, &atoms, &mut serials, &mut out);
+
+	out.push_str(&format!("TORSDOF {}\n", rotatable.len()));
+
+	Ok(out)
+}
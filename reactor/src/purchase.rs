@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+
+use itertools::Itertools;
+use zip_next as zip;
+use zip::ZipWriter;
+
+use chemodots_db as db;
+use chemodots_common as common;
+
+use db::model::PurchasableBuildingBlock;
+
+struct PurchaseEntry {
+	smiles: String,
+	inchikey: Option<String>,
+	refids: BTreeSet<String>,
+	other_providers: BTreeSet<String>,
+	product_counts: BTreeMap<String, i64>,
+	// Best offer among the compounds of the provider
+	price_per_mg: Option<f32>,
+	pack_sizes: BTreeSet<String>,
+	in_stock: Option<bool>,
+	lead_time_days: Option<i32>,
+}
+
+impl PurchaseEntry {
+	fn product_count(&self) -> i64 {
+		self.product_counts.values().sum()
+	}
+}
+
+// Providers listed in `preference` come first, in that order, the others follow by name
+fn provider_rank<'a>(preference: &[String], provider: &'a str) -> (usize, &'a str) {
+	let idx = preference
+		.iter()
+		.position(|e| e == provider)
+		.unwrap_or(preference.len());
+
+	(idx, provider)
+}
+
+/// Writes one purchase sheet per provider.
+/// Each building block is only listed once, for the most preferred provider selling it.
+pub(crate) fn gen_purchase_lists(file_out_zip: &mut ZipWriter<File>, zip_opts: &zip::write::FileOptions, filename_prefix: &str, exp_uuid_str: &str, preference: &[String], rows: impl Iterator<Item = PurchasableBuildingBlock>) {
+	let by_provider = rows
+		.into_group_map_by(|row| row.id)
+		.into_values()
+		.filter_map(|rows| {
+			let provider = rows
+				.iter()
+				.map(|row| row.provider.as_str())
+				.min_by_key(|provider| provider_rank(preference, provider))?
+				.to_owned();
+
+			let mut entry = PurchaseEntry {
+				smiles: rows[0].smiles.clone(),
+				inchikey: rows[0].inchikey.clone(),
+				refids: BTreeSet::new(),
+				other_providers: BTreeSet::new(),
+				product_counts: BTreeMap::new(),
+				price_per_mg: None,
+				pack_sizes: BTreeSet::new(),
+				in_stock: None,
+				lead_time_days: None,
+			};
+
+			for row in rows {
+				// Rows are per compound, so product counts are repeated for each vendor compound
+				entry.product_counts.insert(row.reaction_slug, row.product_count);
+
+				if row.provider == provider {
+					entry.refids.insert(row.refid);
+
+					entry.price_per_mg = entry.price_per_mg.into_iter().chain(row.price_per_mg).reduce(f32::min);
+					entry.pack_sizes.extend(row.pack_sizes.iter().flat_map(|pack_sizes| pack_sizes.split(',')).map(str::to_owned));
+					entry.in_stock = entry.in_stock.into_iter().chain(row.in_stock).reduce(|in_stock0, in_stock1| in_stock0 || in_stock1);
+					entry.lead_time_days = entry.lead_time_days.into_iter().chain(row.lead_time_days).min();
+				} else {
+					entry.other_providers.insert(row.provider);
+				}
+			}
+
+			Some((provider, entry))
+		})
+		.into_group_map();
+
+	by_provider
+		.into_iter()
+		.sorted_by(|(provider0, _), (provider1, _)| provider_rank(preference, provider0).cmp(&provider_rank(preference, provider1)))
+		.for_each(|(provider, entries)| {
+			file_out_zip.start_file(format!("{filename_prefix}_purchase_{}.tsv", common::slugify(&provider)), zip_opts.clone()).unwrap();
+
+			writeln!(file_out_zip, "Refid\tSmiles\tInChIKey\tProductCount\tReactions\tOtherProviders\tPricePerMg\tPackSizes\tInStock\tLeadTimeDays").unwrap();
+			entries
+				.iter()
+				.sorted_by(|entry0, entry1| entry1.product_count().cmp(&entry0.product_count()).then(entry0.smiles.cmp(&entry1.smiles)))
+				.for_each(|entry| {
+					writeln!(file_out_zip, "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+						entry.refids.iter().join(","),
+						entry.smiles,
+						entry.inchikey.as_deref().unwrap_or_default(),
+						entry.product_count(),
+						entry.product_counts.keys().join(","),
+						entry.other_providers.iter().join(","),
+						entry.price_per_mg.map(|price_per_mg| price_per_mg.to_string()).unwrap_or_default(),
+						entry.pack_sizes.iter().join(","),
+						entry.in_stock.map(|in_stock| in_stock.to_string()).unwrap_or_default(),
+						entry.lead_time_days.map(|lead_time_days| lead_time_days.to_string()).unwrap_or_default())
+						.expect(&format!("Failed to write building block to purchase list of {provider} for experiment {exp_uuid_str}"));
+				});
+		});
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use std::ops::Bound;
+use std::path::Path;
+
+use itertools::Itertools;
+use serde_json::Value;
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::depictor::DepictorMutImpl;
+use rdkit_rust::graphmol::moldraw2d::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::graphmol::rwmol::*;
+
+use chemodots_db as db;
+
+use db::custom::RealrangeType;
+use db::model::{Experiment, ExperimentFrag, ExperimentPostprocFilter};
+
+pub const REPORT_FILENAME: &str = "report.html";
+
+const FRAG_IMG_SIZE: u32 = 400;
+
+const PLOTS: [(&str, &str); 6] = [
+	("plot-fsp3.svg", "Fsp³"),
+	("plot-hba.svg", "HBA"),
+	("plot-hbd.svg", "HBD"),
+	("plot-clogp.svg", "cLogP"),
+	("plot-mw.svg", "MW"),
+	("plot-tpsa.svg", "TPSA"),
+];
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:1em 0}\
+th,td{border:1px solid #ccc;padding:.3em .6em;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+.plots{display:flex;flex-wrap:wrap;gap:1em}\
+.grid svg{max-width:100%;height:auto}";
+
+fn escape(text: &str) -> String {
+	text
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+// Inline SVG, without the XML declaration
+fn read_svg(path: impl AsRef<Path>) -> Option<String> {
+	let contents = fs::read_to_string(path).ok()?;
+
+	contents
+		.find("<svg")
+		.map(|idx| contents[idx..].to_owned())
+}
+
+fn fmt_bound<T: std::fmt::Display>(bound: &Bound<T>) -> String {
+	match bound {
+		Bound::Included(value) | Bound::Excluded(value) => value.to_string(),
+		Bound::Unbounded => String::new(),
+	}
+}
+
+fn fmt_f32_range(range: &RealrangeType) -> String {
+	let (min, max) = &range.0;
+	let fmt = |bound: &Bound<f32>| match bound {
+		Bound::Included(value) | Bound::Excluded(value) if value.is_infinite() => String::new(),
+		_ => fmt_bound(bound),
+	};
+
+	format!("{} – {}", fmt(min), fmt(max))
+}
+
+fn fmt_i32_range((min, max): &(Bound<i32>, Bound<i32>)) -> String {
+	let fmt = |bound: &Bound<i32>| match bound {
+		Bound::Included(i32::MIN | i32::MAX) => String::new(),
+		_ => fmt_bound(bound),
+	};
+
+	format!("{} – {}", fmt(min), fmt(max))
+}
+
+// Starting fragment with the selected growing atoms highlighted
+fn frag_svg(ent_frag: &ExperimentFrag) -> Option<String> {
+	let frag_mol = ROMol::new(ROMolFromPickleParams {
+			pickle: &ent_frag.rdpickle
+		})
+		.ok()?;
+
+	let mut frag_mol = RWMol::new(RWMolInitParamsROMol {
+			romol: frag_mol.get_ref(),
+		})
+		.ok()?;
+
+	frag_mol.compute_2d_coords();
+
+	let highlight_atoms = ent_frag.moiety_atoms
+		.iter()
+		.filter_map(|idx_atom| idx_atom.map(|idx_atom| idx_atom as u32))
+		.collect_vec();
+
+	let canvas = new_local!(MolDraw2DSVG);
+	let mut canvas = canvas
+		.init(&MolDraw2DSVGInitParams {
+			width: FRAG_IMG_SIZE,
+			height: FRAG_IMG_SIZE,
+			panel_width: None,
+			panel_height: None,
+		})
+		.ok()?;
+
+	canvas.draw_molecule_with_highlights(&frag_mol, Some(&ent_frag.smiles), &highlight_atoms, &[]);
+	canvas.finish_drawing();
+
+	canvas
+		.get_drawing_text()
+		.ok()
+}
+
+fn counters_table(info: &Value) -> String {
+	let Some(reactions) = info["reactions"].as_array() else {
+		return "<p>No reaction counters available.</p>\n".to_owned();
+	};
+
+	let rows = reactions
+		.iter()
+		.map(|reaction| format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+			escape(reaction["name"].as_str().unwrap_or_default()),
+			reaction["total_bb_cnt"],
+			reaction["reacted_bb_cnt"],
+			reaction["generated_prod_cnt"],
+			reaction["duplicate_prod_cnt"],
+			reaction["shared_prod_cnt"],
+			reaction["undesired_prod_cnt"],
+			reaction["final_prod_cnt"]))
+		.join("");
+
+	format!("<table>\n<tr><th>Reaction</th><th>Building blocks</th><th>Reacted</th><th>Generated</th><th>Duplicates</th><th>Shared</th><th>Undesired</th><th>Final</th></tr>\n{rows}</table>\n")
+}
+
+fn filters_table(ent_filters: &[ExperimentPostprocFilter]) -> String {
+	if ent_filters.is_empty() {
+		return "<p>No filters applied.</p>\n".to_owned();
+	}
+
+	let rows = ent_filters
+		.iter()
+		.map(|ent_filter| format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+			ent_filter.ts.format("%Y-%m-%d %H:%M:%S"),
+			fmt_f32_range(&ent_filter.desc_fsp3),
+			fmt_i32_range(&ent_filter.desc_hba),
+			fmt_i32_range(&ent_filter.desc_hbd),
+			fmt_f32_range(&ent_filter.desc_clogp),
+			fmt_f32_range(&ent_filter.desc_mw),
+			fmt_f32_range(&ent_filter.desc_tpsa),
+			ent_filter.top_n.map(|top_n| top_n.to_string()).unwrap_or_default(),
+			ent_filter.purchasable.map(|purchasable| if purchasable { "only" } else { "excluded" }).unwrap_or_default(),
+			ent_filter.max_price_per_mg.map(|max_price_per_mg| max_price_per_mg.to_string()).unwrap_or_default(),
+			ent_filter.max_lead_time_days.map(|max_lead_time_days| max_lead_time_days.to_string()).unwrap_or_default()))
+		.join("");
+
+	format!("<table>\n<tr><th>Date</th><th>Fsp³</th><th>HBA</th><th>HBD</th><th>cLogP</th><th>MW</th><th>TPSA</th><th>Top N</th><th>Purchasable</th><th>Max price/mg</th><th>Max lead time (days)</th></tr>\n{rows}</table>\n")
+}
+
+/// Writes a single HTML file summarizing the experiment, with every image inlined.
+/// Must be called from the experiment directory, after the raw export (and the filtered one, if any).
+pub fn gen_report(db_pool: &db::DBPool, ent_experiment: &Experiment) -> Result<(), String> {
+	let exp_uuid_str = ent_experiment.uuid.to_string();
+
+	let mut conn = db_pool.get().unwrap();
+
+	let ent_frags = db::model::get_experiment_frags_with_experiment(&mut conn, ent_experiment)
+		.map_err(|_| format!("Failed to fetch fragments of experiment {exp_uuid_str}"))?;
+	let ent_reactions = db::model::get_reactions_with_experiment(&mut conn, ent_experiment)
+		.map_err(|_| format!("Failed to fetch reactions of experiment {exp_uuid_str}"))?
+		.filter_map(|e| e.ok())
+		.unique_by(|ent_reaction| ent_reaction.id)
+		.sorted_by_key(|ent_reaction| ent_reaction.id)
+		.collect_vec();
+	let ent_providers = db::model::get_compound_providers_with_experiment(&mut conn, ent_experiment)
+		.map_err(|_| format!("Failed to fetch providers of experiment {exp_uuid_str}"))?;
+	let ent_filters = db::model::get_experiment_postproc_filters_with_experiment(&mut conn, ent_experiment)
+		.map_err(|_| format!("Failed to fetch filters of experiment {exp_uuid_str}"))?;
+
+	let info: Value = fs::read("info.json")
+		.ok()
+		.and_then(|contents| serde_json::from_slice(&contents).ok())
+		.unwrap_or_default();
+
+	let mut html = String::new();
+
+	html.push_str(&format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n", escape(&ent_experiment.name)));
+	html.push_str(&format!("<h1>{}</h1>\n<p>Experiment {exp_uuid_str}, started {}", escape(&ent_experiment.name), ent_experiment.ts_start.format("%Y-%m-%d %H:%M:%S")));
+	if let Some(duration) = info["duration"].as_str() {
+		html.push_str(&format!(", ran for {duration}"));
+	}
+	html.push_str(".</p>\n");
+
+	html.push_str("<h2>Inputs</h2>\n");
+
+	if let Some(ent_frag) = ent_frags.first() {
+		html.push_str(&format!("<h3>Fragment</h3>\n<p><code>{}</code></p>\n", escape(&ent_frag.smiles)));
+		if let Some(svg) = frag_svg(ent_frag) {
+			html.push_str(&svg);
+			html.push('\n');
+		}
+	}
+
+	html.push_str("<h3>Reactions</h3>\n<ul>\n");
+	ent_reactions
+		.iter()
+		.for_each(|ent_reaction| {
+			html.push_str(&format!("<li>{}", escape(&ent_reaction.name)));
+			if let Some(reference) = ent_reaction.reference.as_deref() {
+				html.push_str(&format!(" ({})", escape(reference)));
+			}
+			html.push_str("</li>\n");
+		});
+	html.push_str("</ul>\n");
+
+	html.push_str(&format!("<h3>Providers</h3>\n<p>{}</p>\n", ent_providers
+		.iter()
+		.map(|ent_provider| escape(&ent_provider.name))
+		.join(", ")));
+
+	if let Some(score_profile) = ent_experiment.score_profile.as_ref() {
+		html.push_str(&format!("<h3>Score profile</h3>\n<pre>{}</pre>\n", escape(&serde_json::to_string_pretty(score_profile).unwrap_or_default())));
+	}
+
+	html.push_str("<h2>Reaction counters</h2>\n");
+	html.push_str(&counters_table(&info));
+
+	html.push_str("<h2>Descriptors</h2>\n<div class=\"plots\">\n");
+	PLOTS
+		.iter()
+		.filter_map(|(filename, caption)| read_svg(filename).map(|svg| (caption, svg)))
+		.for_each(|(caption, svg)| html.push_str(&format!("<figure>{svg}<figcaption>{caption}</figcaption></figure>\n")));
+	html.push_str("</div>\n");
+
+	html.push_str("<h2>Filters</h2>\n");
+	html.push_str(&filters_table(&ent_filters));
+
+	html.push_str("<h2>Products</h2>\n");
+	ent_reactions
+		.iter()
+		.for_each(|ent_reaction| {
+			html.push_str(&format!("<h3>{}</h3>\n", escape(&ent_reaction.name)));
+
+			if let Some(svg) = read_svg(Path::new("raw").join(format!("reaction{}-subset100.svg", ent_reaction.id))) {
+				html.push_str(&format!("<div class=\"grid\">{svg}</div>\n"));
+			}
+
+			if let Some(svg) = read_svg(Path::new("filtered").join(format!("reaction{}-page0.svg", ent_reaction.id))) {
+				html.push_str(&format!("<h4>After the last filter</h4>\n<div class=\"grid\">{svg}</div>\n"));
+			}
+		});
+
+	html.push_str("</body>\n</html>\n");
+
+	fs::write(REPORT_FILENAME, html.as_bytes())
+		.map_err(|_| format!("Failed to write report for experiment {exp_uuid_str}"))
+}
@@ -0,0 +1,171 @@
+use std::collections::{HashMap, VecDeque};
+
+use itertools::Itertools;
+
+use rdkit_rust::*;
+use rdkit_rust::general::props::prelude::*;
+use rdkit_rust::graphmol::chemreactions::reaction::*;
+use rdkit_rust::graphmol::depictor::DepictorMutImpl;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::graphmol::rwmol::*;
+use rdkit_rust::prelude::*;
+
+// Properties set by RDKit on the atoms of the products of `run_reactants`
+const PROP_REACTANT_ATOM_IDX: &str = "react_atom_idx";
+const PROP_REACTION_MAP_NUM: &str = "old_mapno";
+
+/// Atom-mapped reaction SMILES of a single product.
+/// `reactants` are given in output order, each one with its reactant index in the reaction template.
+/// Product atoms matched by the template carry the template map number, the other product atoms
+/// are traced back to the reactant of the mapped atoms they are bonded to.
+pub fn mapped_reaction_smiles(reaction: &impl ChemicalReactionImplRef, reactants: &[(usize, &impl ROMolImplRef)], product: &impl ROMolImplRef) -> Option<String> {
+	let templates = reaction.get_reactants();
+
+	let mut map_num_templates = HashMap::new();
+	for idx_template in 0..templates.size() {
+		let template = templates.get(idx_template).ok()?;
+
+		for idx_atom in 0..template.get_num_atoms() {
+			let map_num = template.get_atom(idx_atom).ok()?.get_atom_map_num();
+			if map_num != 0 {
+				map_num_templates.insert(map_num, idx_template);
+			}
+		}
+	}
+
+	let atom_count = product.get_num_atoms();
+
+	// (template reactant index, reactant atom index) of each product atom
+	let mut origins: Vec<Option<(usize, u32)>> = vec![None; atom_count as usize];
+	let mut mapped = vec![false; atom_count as usize];
+
+	for idx_atom in 0..atom_count {
+		let atom = product.get_atom(idx_atom).ok()?;
+
+		let Ok(idx_reactant_atom) = atom.get_prop_u32(PROP_REACTANT_ATOM_IDX) else {
+			continue
+		};
+
+		if let Ok(map_num) = atom.get_prop_i32(PROP_REACTION_MAP_NUM) {
+			mapped[idx_atom as usize] = true;
+			origins[idx_atom as usize] = map_num_templates
+				.get(&map_num)
+				.map(|idx_template| (*idx_template, idx_reactant_atom));
+		}
+	}
+
+	let mut queue: VecDeque<_> = (0..atom_count)
+		.filter(|idx_atom| origins[*idx_atom as usize].is_some())
+		.collect();
+
+	// Bonds between reactants are only formed by mapped atoms, so unmapped atoms never cross reactants
+	while let Some(idx_atom) = queue.pop_front() {
+		let (idx_template, _) = origins[idx_atom as usize]?;
+
+		for idx_nbr in product.get_atom_neighbors(idx_atom) {
+			if mapped[idx_nbr as usize] || origins[idx_nbr as usize].is_some() {
+				continue
+			}
+
+			let Ok(idx_reactant_atom) = product.get_atom(idx_nbr).ok()?.get_prop_u32(PROP_REACTANT_ATOM_IDX) else {
+				continue
+			};
+
+			origins[idx_nbr as usize] = Some((idx_template, idx_reactant_atom));
+			queue.push_back(idx_nbr);
+		}
+	}
+
+	let mut reactant_mols = reactants
+		.iter()
+		.map(|(idx_template, mol)| RWMol::new(RWMolInitParamsROMol {
+				romol: mol.get_ref(),
+			})
+			.ok()
+			.map(|mol| (*idx_template, mol)))
+		.collect::<Option<Vec<_>>>()?;
+
+	let mut product_mol = RWMol::new(RWMolInitParamsROMol {
+			romol: product.get_ref(),
+		})
+		.ok()?;
+
+	for (idx_atom, origin) in origins.into_iter().enumerate() {
+		let Some((idx_template, idx_reactant_atom)) = origin else {
+			continue
+		};
+
+		let Some((_, reactant_mol)) = reactant_mols
+			.iter_mut()
+			.find(|(idx, _)| *idx == idx_template) else {
+			continue
+		};
+
+		let map_num = idx_atom as i32 + 1;
+
+		product_mol.get_atom_mut(idx_atom as u32).ok()?.set_atom_map_num(map_num);
+		reactant_mol.get_atom_mut(idx_reactant_atom).ok()?.set_atom_map_num(map_num);
+	}
+
+	let reactant_smiles = reactant_mols
+		.iter()
+		.map(|(_, mol)| mol.to_smiles().ok())
+		.collect::<Option<Vec<_>>>()?
+		.join(".");
+
+	Some(format!("{reactant_smiles}>>{}", product_mol.to_smiles().ok()?))
+}
+
+fn mol_block(smiles: &str) -> Option<String> {
+	let mol = new_local!(RWMol);
+	let mut mol = mol
+		.init(ParseSmilesParams {
+			text: smiles,
+			debug_parse: Default::default(),
+			sanitize: Default::default(),
+			replacements: (),
+		})
+		.ok()?;
+
+	mol.compute_2d_coords();
+
+	mol.to_mol_block().ok()
+}
+
+pub fn rdf_header() -> String {
+	format!("$RDFILE 1\n$DATM    {}\n", chrono::Utc::now().format("%m/%d/%y %H:%M"))
+}
+
+/// One RDF entry ($RFMT) holding the mapped reaction of a product as a RXN block, along with its data fields.
+pub fn rdf_entry(route_smiles: &str, name: &str, data: &[(&str, &str)]) -> Option<String> {
+	let (reactants, products) = route_smiles.split_once(">>")?;
+
+	let reactant_blocks = reactants
+		.split('.')
+		.map(mol_block)
+		.collect::<Option<Vec<_>>>()?;
+	let product_blocks = products
+		.split('.')
+		.map(mol_block)
+		.collect::<Option<Vec<_>>>()?;
+
+	let mut entry = format!("$RFMT\n$RXN\n{name}\n  ChemoDOTS\n\n{:>3}{:>3}\n", reactant_blocks.len(), product_blocks.len());
+
+	reactant_blocks
+		.iter()
+		.chain(product_blocks.iter())
+		.for_each(|block| {
+			entry.push_str("$MOL\n");
+			entry.push_str(block);
+			if !block.ends_with('\n') {
+				entry.push('\n');
+			}
+		});
+
+	entry.push_str(&data
+		.iter()
+		.map(|(key, value)| format!("$DTYPE {key}\n$DATUM {value}\n"))
+		.join(""));
+
+	Some(entry)
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use itertools::{Itertools, MinMaxResult};
+use serde_json::{json, Value};
+use zip_next as zip;
+use zip::ZipWriter;
+
+use rdkit_rust::*;
+use rdkit_rust::graphmol::moldraw2d::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
+
+use chemodots_db as db;
+
+use db::model::Reaction;
+
+use crate::ExportedProduct;
+
+// Only the most populated scaffolds get an image grid
+const MAX_SCAFFOLD_GRIDS: usize = 20;
+
+fn desc_range(values: impl Iterator<Item = f64>) -> Value {
+	match values.minmax_by(f64::total_cmp) {
+		MinMaxResult::NoElements => Value::Null,
+		MinMaxResult::OneElement(a) => json!([a, a]),
+		MinMaxResult::MinMax(a, b) => json!([a, b]),
+	}
+}
+
+fn count_by<'a>(it: impl Iterator<Item = &'a str>) -> Value {
+	let counts = it
+		.counts()
+		.into_iter()
+		.sorted_by(|(smiles0, count0), (smiles1, count1)| count1.cmp(count0).then(smiles0.cmp(smiles1)))
+		.map(|(smiles, count)| json!({
+			"smiles": smiles,
+			"count": count,
+		}))
+		.collect_vec();
+
+	Value::Array(counts)
+}
+
+pub(crate) fn gen_scaffold_summary(path_prefix: &Path, file_out_zip: &mut ZipWriter<File>, zip_opts: &zip::write::FileOptions, filename_prefix: &str, exp_uuid_str: &str, gen_img: bool, ent_reactions: &[Reaction], frag_ring_systems: &[String], products: &[ExportedProduct], bb_scaffolds: &[Option<String>]) {
+	let reaction_slugs: HashMap<_, _> = ent_reactions
+		.iter()
+		.map(|ent_reaction| (ent_reaction.id, ent_reaction.slug.as_str()))
+		.collect();
+
+	// Products are expected to be sorted already, the order is kept inside each scaffold
+	let scaffolds = products
+		.iter()
+		.into_group_map_by(|prod| prod.scaffold_smiles.as_deref().unwrap_or(""))
+		.into_iter()
+		.sorted_by(|(smiles0, prods0), (smiles1, prods1)| prods1.len().cmp(&prods0.len()).then(smiles0.cmp(smiles1)))
+		.collect_vec();
+
+	let json_scaffolds = scaffolds
+		.iter()
+		.enumerate()
+		.map(|(rank, (smiles, prods))| {
+			let generic_smiles = prods
+				.first()
+				.and_then(|prod| prod.generic_scaffold_smiles.as_deref())
+				.unwrap_or("");
+
+			let reactions: serde_json::Map<_, _> = prods
+				.iter()
+				.map(|prod| prod.id_reaction)
+				.counts()
+				.into_iter()
+				.sorted()
+				.map(|(id_reaction, count)| (reaction_slugs.get(&id_reaction).copied().unwrap_or_default().to_owned(), json!(count)))
+				.collect();
+
+			json!({
+				"rank": rank,
+				"smiles": smiles,
+				"generic_smiles": generic_smiles,
+				"count": prods.len(),
+				"reactions": reactions,
+				"new_ring_systems": count_by(prods
+					.iter()
+					.flat_map(|prod| prod.new_ring_systems
+						.as_deref()
+						.unwrap_or("")
+						.split('.')
+						.filter(|smiles| !smiles.is_empty()))),
+				"ranges": {
+					"fsp3": desc_range(prods.iter().map(|prod| prod.fsp3 as f64)),
+					"hba": desc_range(prods.iter().map(|prod| prod.hba as f64)),
+					"hbd": desc_range(prods.iter().map(|prod| prod.hbd as f64)),
+					"clogp": desc_range(prods.iter().map(|prod| prod.clogp as f64)),
+					"mw": desc_range(prods.iter().map(|prod| prod.mw)),
+					"tpsa": desc_range(prods.iter().map(|prod| prod.tpsa as f64)),
+				},
+			})
+		})
+		.collect_vec();
+
+	let res_json = json!({
+		"fragment_ring_systems": frag_ring_systems,
+		"scaffolds": json_scaffolds,
+		"generic_scaffolds": count_by(products
+			.iter()
+			.map(|prod| prod.generic_scaffold_smiles.as_deref().unwrap_or(""))),
+		"new_ring_systems": count_by(products
+			.iter()
+			.flat_map(|prod| prod.new_ring_systems
+				.as_deref()
+				.unwrap_or("")
+				.split('.')
+				.filter(|smiles| !smiles.is_empty()))),
+		"building_blocks": {
+			"scaffolds": count_by(bb_scaffolds
+				.iter()
+				.map(|smiles| smiles.as_deref().unwrap_or(""))),
+		},
+	});
+
+	let res_json = res_json.to_string();
+
+	fs::write(Path::join(path_prefix, "scaffolds.json"), res_json.as_bytes())
+		.expect(&format!("Failed to write scaffolds summary for experiment {exp_uuid_str}"));
+
+	file_out_zip.start_file(format!("{filename_prefix}_scaffolds.json"), zip_opts.clone()).unwrap();
+	file_out_zip.write_all(res_json.as_bytes())
+		.expect(&format!("Failed to write scaffolds summary to archive for experiment {exp_uuid_str}"));
+
+	if !gen_img {
+		return;
+	}
+
+	scaffolds
+		.iter()
+		.enumerate()
+		// Acyclic products have no scaffold
+		.filter(|(_, (smiles, _))| !smiles.is_empty())
+		.take(MAX_SCAFFOLD_GRIDS)
+		.for_each(|(rank, (_, prods))| {
+			let num_products = 100;
+
+			let (mols, legends): (Vec<_>, Vec<_>) = prods
+				.iter()
+				.take(num_products)
+				.map(|prod| (&prod.mol, prod.legend.as_str()))
+				.unzip();
+
+			let canvas = new_local!(MolDraw2DSVG);
+			let mut canvas = canvas
+				.init(&MolDraw2DSVGInitParams {
+					width: 2560,
+					height: 2560,
+					panel_width: Some(256),
+					panel_height: Some(256),
+				})
+				.unwrap();
+
+			if !mols.is_empty() {
+				canvas.draw_molecules(&mols, Some(&legends));
+			}
+
+			canvas.finish_drawing();
+
+			let img_subset_text = canvas
+				.get_drawing_text()
+				.unwrap();
+
+			fs::write(Path::join(path_prefix, format!("scaffold{rank}-subset100.svg")), img_subset_text.as_bytes())
+				.expect(&format!("Failed to write products subset(100) SVG file for experiment {exp_uuid_str} and scaffold {rank}"));
+		});
+}
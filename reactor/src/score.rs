@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+/// Product descriptors that can be scored, as stored in `experiment_product`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Descriptor {
+	Fsp3,
+	Hba,
+	Hbd,
+	Clogp,
+	Mw,
+	Tpsa,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Descriptors {
+	pub fsp3: f32,
+	pub hba: i32,
+	pub hbd: i32,
+	pub clogp: f32,
+	pub mw: f32,
+	pub tpsa: f32,
+}
+
+impl Descriptors {
+	fn get(&self, descriptor: Descriptor) -> f32 {
+		match descriptor {
+			Descriptor::Fsp3 => self.fsp3,
+			Descriptor::Hba => self.hba as f32,
+			Descriptor::Hbd => self.hbd as f32,
+			Descriptor::Clogp => self.clogp,
+			Descriptor::Mw => self.mw,
+			Descriptor::Tpsa => self.tpsa,
+		}
+	}
+}
+
+/// Piecewise linear desirability functions, mapping a descriptor value to [0, 1].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Desirability {
+	/// 1 up to `best`, 0 from `worst`
+	Decreasing { best: f32, worst: f32 },
+	/// 0 up to `worst`, 1 from `best`
+	Increasing { worst: f32, best: f32 },
+	/// 1 within [lower_best, upper_best], 0 outside of [lower_worst, upper_worst]
+	Hump { lower_worst: f32, lower_best: f32, upper_best: f32, upper_worst: f32 },
+}
+
+fn ramp(value: f32, zero: f32, one: f32) -> f32 {
+	if zero == one {
+		return if value >= one { 1.0 } else { 0.0 };
+	}
+
+	((value - zero) / (one - zero)).clamp(0.0, 1.0)
+}
+
+impl Desirability {
+	pub fn eval(&self, value: f32) -> f32 {
+		match *self {
+			Self::Decreasing { best, worst } => 1.0 - ramp(value, best, worst),
+			Self::Increasing { worst, best } => ramp(value, worst, best),
+			Self::Hump { lower_worst, lower_best, upper_best, upper_worst } =>
+				ramp(value, lower_worst, lower_best).min(1.0 - ramp(value, upper_best, upper_worst)),
+		}
+	}
+}
+
+fn default_weight() -> f32 {
+	1.0
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScoreTerm {
+	pub descriptor: Descriptor,
+	#[serde(default = "default_weight")]
+	pub weight: f32,
+	pub desirability: Desirability,
+}
+
+/// Weighted mean of desirabilities, scores are within [0, 1].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScoreProfile {
+	pub name: String,
+	pub terms: Vec<ScoreTerm>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScorePreset {
+	CnsMpo,
+}
+
+/// A profile as given by the user: either the name of a preset or a full profile.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ScoreProfileQuery {
+	Preset(ScorePreset),
+	Custom(ScoreProfile),
+}
+
+impl From<ScoreProfileQuery> for ScoreProfile {
+	fn from(value: ScoreProfileQuery) -> Self {
+		match value {
+			ScoreProfileQuery::Preset(ScorePreset::CnsMpo) => Self::cns_mpo(),
+			ScoreProfileQuery::Custom(profile) => profile,
+		}
+	}
+}
+
+impl ScoreProfile {
+	/// CNS MPO (Wager et al. 2010) restricted to the stored descriptors.
+	/// ClogD and pKa are not computed, so the score is an approximation over 4 of the 6 terms.
+	pub fn cns_mpo() -> Self {
+		Self {
+			name: "cns_mpo".to_owned(),
+			terms: vec![
+				ScoreTerm {
+					descriptor: Descriptor::Clogp,
+					weight: 1.0,
+					desirability: Desirability::Decreasing { best: 3.0, worst: 5.0 },
+				},
+				ScoreTerm {
+					descriptor: Descriptor::Mw,
+					weight: 1.0,
+					desirability: Desirability::Decreasing { best: 360.0, worst: 500.0 },
+				},
+				ScoreTerm {
+					descriptor: Descriptor::Tpsa,
+					weight: 1.0,
+					desirability: Desirability::Hump { lower_worst: 20.0, lower_best: 40.0, upper_best: 90.0, upper_worst: 120.0 },
+				},
+				ScoreTerm {
+					descriptor: Descriptor::Hbd,
+					weight: 1.0,
+					desirability: Desirability::Decreasing { best: 0.5, worst: 3.5 },
+				},
+			],
+		}
+	}
+
+	pub fn score(&self, descs: &Descriptors) -> f32 {
+		let (total, total_weight) = self.terms
+			.iter()
+			.fold((0.0, 0.0), |(total, total_weight), term| (
+				total + term.weight * term.desirability.eval(descs.get(term.descriptor)),
+				total_weight + term.weight,
+			));
+
+		if total_weight <= 0.0 {
+			return 0.0;
+		}
+
+		total / total_weight
+	}
+}
@@ -8,12 +8,15 @@ use rdkit_rust::graphmol::moldraw2d::*;
 use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
 use serde_json::{json, Value};
 
-use rdkit_rust::graphmol::substruct::substructmatch::{MatchVectTypeVec, MatchVectTypeVecInitParamsFromSubstructMatch, MatchVectTypeVecImplRef};
 use rdkit_rust::prelude::*;
 use rdkit_rust::graphmol::chemreactions::reaction::*;
+use rdkit_rust::graphmol::romol::*;
 use rdkit_rust::graphmol::rwmol::*;
 use rdkit_rust::*;
 
+mod origin;
+mod probe;
+
 pub fn mol_info(frag_mol: &str) {
 	let frag = new_local!(RWMol);
 	let frag = frag.init(ParseMolBlockParams {
@@ -60,6 +63,7 @@ pub fn mol_info(frag_mol: &str) {
 	println!("{}", res_json.to_string());
 }
 
+// Reactions with a reactant template matching the selected atoms, with the other reacting sites of the fragment as warnings
 pub fn compatible_reactions_probe(db_pool: &db::DBPool, frag_mol: &str, idx_atoms: &[i32]) {
 	let mut conn = db_pool.get().unwrap();
 
@@ -74,7 +78,7 @@ pub fn compatible_reactions_probe(db_pool: &db::DBPool, frag_mol: &str, idx_atom
 
 	let json_reactions = db::model::Reaction::get_all(&mut conn).unwrap()
 		.filter_map(|e| e.ok())
-		.filter(|ent_reaction| -> bool {
+		.filter_map(|ent_reaction| {
 			let reaction = new_local!(ChemicalReaction);
 
 			let reaction = reaction.init(ChemicalReactionFromPickleParams {
@@ -82,42 +86,20 @@ pub fn compatible_reactions_probe(db_pool: &db::DBPool, frag_mol: &str, idx_atom
 				})
 				.unwrap();
 
-			let reactants = reaction.get_reactants();
-			let reactant_count = reactants.size();
-
-			for reactant_idx in 0..reactant_count {
-				let reactant = reactants.get(reactant_idx).unwrap();
-
-				let matches = new_local!(MatchVectTypeVec);
-				let matches = matches
-					.init(&MatchVectTypeVecInitParamsFromSubstructMatch::new(&frag, &reactant));
-				if matches.is_err() {
-					continue;
-				}
-				let matches = matches.unwrap();
-				let entry_count = matches.len();
-
-				for idx_entry in 0..entry_count {
-					let pair_count = matches.entry_len(idx_entry);
-
-					for idx_pair in 0..pair_count {
-						let (_, idx_frag_atom) = matches.entry_get_atom_pair(idx_entry, idx_pair).unwrap();
-
-						let found = idx_atoms.iter().contains(&idx_frag_atom);
-						if found {
-							return true;
-						}
-					}
-				}
+			let sites = probe::reactant_sites(&frag, &reaction);
+
+			let compatible = sites
+				.iter()
+				.any(|site| site.atoms.iter().any(|idx_atom| idx_atoms.contains(idx_atom)));
+			if !compatible {
+				return None;
 			}
 
-			return false;
-		})
-		.map(|ent_reaction| {
-			json!({
+			Some(json!({
 				"id": ent_reaction.id,
 				"name": ent_reaction.name,
-			})
+				"warnings": probe::cross_reactivity_warnings(&sites, idx_atoms),
+			}))
 		})
 		.collect_vec();
 
@@ -125,3 +107,68 @@ pub fn compatible_reactions_probe(db_pool: &db::DBPool, frag_mol: &str, idx_atom
 
 	println!("{}", res_json.to_string());
 }
+
+// The vendor record of a compound next to the building blocks it was standardized into
+pub fn compound_origin(db_pool: &db::DBPool, provider_name: &str, refid: &str) -> Result<(), &'static str> {
+	let mut conn = db_pool.get().unwrap();
+
+	let ent_compound = db::model::get_compound_by_provider_and_refid(&mut conn, provider_name, refid)
+		.map_err(|_| "Unknown compound")?;
+
+	let original = new_local!(RWMol);
+	let original = match (&ent_compound.sdf, &ent_compound.smiles) {
+		(Some(sdf), _) => original.init(ParseMolBlockParams {
+				mol_block: sdf,
+				sanitize: Some(false),
+				remove_hs: Some(false),
+				strict_parsing: Default::default(),
+			}),
+		(None, Some(smiles)) => original.init(ParseSmilesParams {
+				text: smiles,
+				debug_parse: Default::default(),
+				sanitize: Some(false),
+				replacements: (),
+			}),
+		(None, None) => return Err("The vendor record was not kept for this compound"),
+	}.map_err(|_| "Invalid vendor record")?;
+
+	let original_summary = origin::StructureSummary::new(&original);
+
+	let json_building_blocks = db::model::get_building_blocks_with_compound(&mut conn, &ent_compound).unwrap()
+		.into_iter()
+		.map(|(ent_origin, ent_building_block)| {
+			let mol = ROMol::new(ROMolFromPickleParams {
+					pickle: &ent_building_block.rdpickle
+				})
+				.unwrap();
+
+			let summary = origin::StructureSummary::new(&mol);
+
+			json!({
+				"id": ent_building_block.id,
+				"smiles": ent_building_block.smiles,
+				"svg": origin::draw_svg(&mol),
+				"catalog_snapshot_from": ent_origin.id_catalog_snapshot_from,
+				"catalog_snapshot_to": ent_origin.id_catalog_snapshot_to,
+				"changes": origin::standardization_changes(&original_summary, &summary),
+			})
+		})
+		.collect_vec();
+
+	let res_json = json!({
+		"provider": provider_name,
+		"refid": ent_compound.refid,
+		"available": ent_compound.available,
+		"original": {
+			"smiles": original_summary.smiles,
+			"svg": origin::draw_svg(&original),
+			"sdf": ent_compound.sdf,
+			"props": ent_compound.sdf.as_deref().map(origin::sd_props),
+		},
+		"building_blocks": json_building_blocks,
+	});
+
+	println!("{}", res_json.to_string());
+
+	Ok(())
+}
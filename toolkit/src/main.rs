@@ -29,6 +29,12 @@ fn main() -> Result<(), &'static str> {
 			toolkit::compatible_reactions_probe(&db_pool, frag_mol, &atoms);
 			Ok(())
 		},
+		"compound_origin" => {
+			let provider = v["provider"].as_str().unwrap();
+			let refid = v["refid"].as_str().unwrap();
+
+			toolkit::compound_origin(&db_pool, provider, refid)
+		},
 		_ => Err("Invalid 'mode'")
 	};
 
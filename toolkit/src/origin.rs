@@ -0,0 +1,106 @@
+use itertools::Itertools;
+use serde_json::{json, Map, Value};
+
+use rdkit_rust::graphmol::moldraw2d::*;
+use rdkit_rust::graphmol::moldraw2d::moldraw2dsvg::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::prelude::*;
+use rdkit_rust::*;
+
+/// Counts compared between the vendor structure and the standardized building block.
+pub(crate) struct StructureSummary {
+	pub smiles: String,
+	pub fragments: usize,
+	pub heavy_atoms: u32,
+	pub explicit_hs: u32,
+	pub formal_charge: i32,
+	pub isotopes: u32,
+}
+
+impl StructureSummary {
+	pub fn new(mol: &impl ROMolImplRef) -> Self {
+		let atoms = (0..mol.get_num_atoms())
+			.filter_map(|idx| mol.get_atom(idx).ok())
+			.collect_vec();
+
+		let smiles = mol.to_smiles().unwrap_or_default();
+
+		Self {
+			fragments: smiles.split('.').count(),
+			smiles,
+			heavy_atoms: mol.get_num_heavy_atoms(),
+			explicit_hs: atoms.iter().filter(|atom| atom.get_atomic_num() == 1).count() as u32,
+			formal_charge: atoms.iter().map(|atom| atom.get_formal_charge()).sum(),
+			isotopes: atoms.iter().filter(|atom| atom.get_isotope() != 0).count() as u32,
+		}
+	}
+}
+
+pub(crate) fn draw_svg(mol: &impl ROMolImplRef) -> String {
+	let drawer = new_local!(MolDraw2DSVG);
+	let mut drawer = drawer.init(&MolDraw2DSVGInitParams {
+		width: 256,
+		height: 256,
+		panel_width: Some(256),
+		panel_height: Some(256),
+	}).unwrap();
+
+	drawer.draw_molecule(mol, None);
+	drawer.finish_drawing();
+
+	drawer.get_drawing_text()
+}
+
+/// Data items of an SD record ("> <NAME>" followed by the value lines).
+pub(crate) fn sd_props(sdf: &str) -> Map<String, Value> {
+	let mut props = Map::new();
+	let mut lines = sdf
+		.lines()
+		.skip_while(|line| !line.starts_with("M  END"));
+
+	while let Some(line) = lines.next() {
+		let Some((_, name)) = line.strip_prefix('>').and_then(|line| line.split_once('<')) else {
+			continue;
+		};
+		let Some((name, _)) = name.split_once('>') else {
+			continue;
+		};
+
+		let value = lines
+			.by_ref()
+			.take_while(|line| !line.trim().is_empty())
+			.join("\n");
+
+		props.insert(name.to_owned(), Value::String(value));
+	}
+
+	props
+}
+
+/// What standardization changed, in plain words.
+pub(crate) fn standardization_changes(original: &StructureSummary, standardized: &StructureSummary) -> Vec<Value> {
+	let mut changes = vec![];
+
+	if original.fragments > standardized.fragments {
+		changes.push(json!(format!("Kept the largest of {} fragments (salts and solvents removed)", original.fragments)));
+	}
+	if original.formal_charge != standardized.formal_charge {
+		changes.push(json!(format!("Net charge {} -> {} (uncharged or reionized)", original.formal_charge, standardized.formal_charge)));
+	}
+	if original.explicit_hs != standardized.explicit_hs {
+		changes.push(json!(format!("Explicit hydrogens {} -> {}", original.explicit_hs, standardized.explicit_hs)));
+	}
+	if original.isotopes != standardized.isotopes {
+		changes.push(json!(format!("Isotope labels {} -> {}", original.isotopes, standardized.isotopes)));
+	}
+	if original.heavy_atoms != standardized.heavy_atoms {
+		changes.push(json!(format!("Heavy atoms {} -> {}", original.heavy_atoms, standardized.heavy_atoms)));
+	}
+
+	// Remaining differences: bond orders, stereochemistry or tautomer
+	if changes.is_empty() && original.smiles != standardized.smiles {
+		changes.push(json!("Same atoms, different bonds or stereochemistry (e.g. tautomer)"));
+	}
+
+	changes
+}
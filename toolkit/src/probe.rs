@@ -0,0 +1,81 @@
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use rdkit_rust::graphmol::chemreactions::reaction::*;
+use rdkit_rust::graphmol::romol::*;
+use rdkit_rust::graphmol::substruct::substructmatch::{MatchVectTypeVec, MatchVectTypeVecInitParamsFromSubstructMatch, MatchVectTypeVecImplRef};
+use rdkit_rust::prelude::*;
+use rdkit_rust::*;
+
+/// Atoms of the fragment matched by a reactant template, one entry per match.
+pub(crate) struct ReactantSite {
+	pub reactant_idx: usize,
+	pub atoms: Vec<i32>,
+}
+
+pub(crate) fn reactant_sites(frag: &impl ROMolImplRef, reaction: &impl ChemicalReactionImplRef) -> Vec<ReactantSite> {
+	let reactants = reaction.get_reactants();
+	let reactant_count = reactants.size();
+
+	(0..reactant_count)
+		.flat_map(|reactant_idx| {
+			let reactant = reactants.get(reactant_idx).unwrap();
+
+			let matches = new_local!(MatchVectTypeVec);
+			let Ok(matches) = matches
+				.init(&MatchVectTypeVecInitParamsFromSubstructMatch::new(frag, &reactant)) else {
+				return vec![];
+			};
+
+			(0..matches.len())
+				.map(|idx_entry| {
+					let atoms = (0..matches.entry_len(idx_entry))
+						.filter_map(|idx_pair| matches.entry_get_atom_pair(idx_entry, idx_pair).ok())
+						.map(|(_, idx_frag_atom)| idx_frag_atom)
+						.sorted()
+						.collect_vec();
+
+					ReactantSite {
+						reactant_idx,
+						atoms,
+					}
+				})
+				.collect_vec()
+		})
+		.collect_vec()
+}
+
+/// Sites of the fragment reacting besides the selected one.
+///
+/// The reactor only protects these atoms in silico, so each one is reported with a severity:
+/// - "high": another copy of the selected group, the reaction would not be selective
+/// - "medium": a group matching another reactant template, the fragment may react with itself
+pub(crate) fn cross_reactivity_warnings(sites: &[ReactantSite], idx_atoms: &[i32]) -> Vec<Value> {
+	let selected_reactants = sites
+		.iter()
+		.filter(|site| site.atoms.iter().any(|idx_atom| idx_atoms.contains(idx_atom)))
+		.map(|site| site.reactant_idx)
+		.unique()
+		.collect_vec();
+
+	sites
+		.iter()
+		.filter(|site| !site.atoms.iter().any(|idx_atom| idx_atoms.contains(idx_atom)))
+		// Overlapping matches of the same template are a single site
+		.unique_by(|site| (site.reactant_idx, site.atoms.clone()))
+		.map(|site| {
+			let (severity, suggestion) = if selected_reactants.contains(&site.reactant_idx) {
+				("high", "Another site of the fragment matches the same reactant: protect it or choose another reaction.")
+			} else {
+				("medium", "The fragment also matches the other reactant and may react with itself: protect this group or choose another reaction.")
+			};
+
+			json!({
+				"reactant_idx": site.reactant_idx,
+				"atoms": site.atoms,
+				"severity": severity,
+				"suggestion": suggestion,
+			})
+		})
+		.collect_vec()
+}
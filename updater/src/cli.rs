@@ -0,0 +1,78 @@
+pub const USAGE: &str = "\
+Usage: chemodots-updater [COMMAND] [OPTIONS]
+
+Commands:
+  update     Run bootstrap, import, reactants and vacuum in order (default)
+  bootstrap  Create or update the reactions, moieties and providers
+  import     Import the catalogs of the providers
+  reactants  Match the new building blocks against the reactions
+  vacuum     Optimize the database
+  status     Show the last catalog snapshot and the catalog counts
+
+Options:
+  --full     Withdraw every compound before importing (update, import)
+  --dry-run  Report what would be done without writing to the database
+  --help     Show this message";
+
+/// Steps of an update, `Update` runs them all in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+	Update,
+	Bootstrap,
+	Import,
+	Reactants,
+	Vacuum,
+	Status,
+	Help,
+}
+
+impl Command {
+	pub fn runs(self, step: Command) -> bool {
+		self == step || self == Command::Update
+	}
+}
+
+#[derive(Debug)]
+pub struct Args {
+	pub command: Command,
+	/// Full rebuild of the catalog, the default only imports the differences with the previous update
+	pub full: bool,
+	pub dry_run: bool,
+}
+
+impl Args {
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+		let mut command = None;
+		let mut full = false;
+		let mut dry_run = false;
+
+		for arg in args {
+			match arg.as_str() {
+				"--full" => full = true,
+				"--dry-run" => dry_run = true,
+				"--help" | "-h" => command = Some(Command::Help),
+				arg if arg.starts_with('-') => Err(format!("Unknown option {arg}"))?,
+				arg if command.is_some() => Err(format!("Unexpected argument {arg}"))?,
+				"update" => command = Some(Command::Update),
+				"bootstrap" => command = Some(Command::Bootstrap),
+				"import" => command = Some(Command::Import),
+				"reactants" => command = Some(Command::Reactants),
+				"vacuum" => command = Some(Command::Vacuum),
+				"status" => command = Some(Command::Status),
+				arg => Err(format!("Unknown command {arg}"))?,
+			}
+		}
+
+		let command = command.unwrap_or(Command::Update);
+
+		if full && !command.runs(Command::Import) {
+			Err("--full only applies to update and import".to_owned())?;
+		}
+
+		Ok(Self {
+			command,
+			full,
+			dry_run,
+		})
+	}
+}
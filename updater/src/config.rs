@@ -0,0 +1,373 @@
+use std::fs;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PROVIDERS_CONFIG: &str = "providers.json";
+
+pub const DEFAULT_IMPORT_REPORT: &str = "import-report.jsonl";
+
+fn default_import_report() -> String {
+	DEFAULT_IMPORT_REPORT.to_owned()
+}
+
+fn default_id_fields() -> Vec<String> {
+	["ID", "Id", "id"]
+		.into_iter()
+		.map(str::to_owned)
+		.collect()
+}
+
+fn default_smiles_column() -> String {
+	"smiles".to_owned()
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+	Sdf,
+	/// Delimited text (SMILES, CSV, TSV) with one compound per line
+	Smiles,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputCompression {
+	None,
+	Gzip,
+	Zstd,
+	Zip,
+}
+
+/// A catalog file, either given by its path alone or with explicit options.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "InputConfigRepr")]
+pub struct InputConfig {
+	/// Relative to the working directory
+	pub path: String,
+	/// Guessed from the file extension when missing
+	pub format: Option<InputFormat>,
+	/// Guessed from the file extension when missing
+	pub compression: Option<InputCompression>,
+	/// Column separator of delimited files, guessed from the file extension when missing
+	pub delimiter: Option<char>,
+	/// Whether the first line of delimited files names the columns,
+	/// by default only when no columns are given and the file is not a .smi one
+	pub header: Option<bool>,
+	/// Names of the columns of delimited files, replacing the header
+	pub columns: Vec<String>,
+	/// Column of delimited files holding the structure (case insensitive)
+	pub smiles_column: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum InputConfigRepr {
+	Path(String),
+	Detailed {
+		path: String,
+		format: Option<InputFormat>,
+		compression: Option<InputCompression>,
+		delimiter: Option<char>,
+		header: Option<bool>,
+		#[serde(default)]
+		columns: Vec<String>,
+		#[serde(default = "default_smiles_column")]
+		smiles_column: String,
+	},
+}
+
+impl From<InputConfigRepr> for InputConfig {
+	fn from(repr: InputConfigRepr) -> Self {
+		match repr {
+			InputConfigRepr::Path(path) => Self {
+				path,
+				format: None,
+				compression: None,
+				delimiter: None,
+				header: None,
+				columns: vec![],
+				smiles_column: default_smiles_column(),
+			},
+			InputConfigRepr::Detailed { path, format, compression, delimiter, header, columns, smiles_column } => Self {
+				path,
+				format,
+				compression,
+				delimiter,
+				header,
+				columns,
+				smiles_column,
+			},
+		}
+	}
+}
+
+impl InputConfig {
+	fn extension(&self) -> String {
+		self.path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default()
+	}
+
+	// Extension of the file once decompressed (e.g. "sdf" for "catalog.sdf.gz")
+	fn inner_extension(&self) -> String {
+		let path = match self.compression() {
+			InputCompression::None => self.path.as_str(),
+			_ => self.path.rsplit_once('.').map_or(self.path.as_str(), |(stem, _)| stem),
+		};
+
+		path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default()
+	}
+
+	pub fn compression(&self) -> InputCompression {
+		self.compression.unwrap_or_else(|| match self.extension().as_str() {
+			"gz" => InputCompression::Gzip,
+			"zst" | "zstd" => InputCompression::Zstd,
+			"zip" => InputCompression::Zip,
+			_ => InputCompression::None,
+		})
+	}
+
+	pub fn format(&self) -> InputFormat {
+		self.format.unwrap_or_else(|| match self.inner_extension().as_str() {
+			"smi" | "smiles" | "csv" | "tsv" | "txt" => InputFormat::Smiles,
+			_ => InputFormat::Sdf,
+		})
+	}
+
+	pub fn delimiter(&self) -> u8 {
+		self.delimiter.map_or_else(|| match self.inner_extension().as_str() {
+			"csv" => b',',
+			"smi" | "smiles" => b' ',
+			_ => b'\t',
+		}, |delimiter| delimiter as u8)
+	}
+
+	pub fn header(&self) -> bool {
+		self.header.unwrap_or_else(|| self.columns.is_empty() && !matches!(self.inner_extension().as_str(), "smi" | "smiles"))
+	}
+
+	/// Fields separated by any run of spaces or tabs, as in .smi files
+	pub fn whitespace_delimited(&self) -> bool {
+		self.delimiter() == b' '
+	}
+
+	/// Column names of delimited files without a header: the SMILES .smi layout
+	pub fn columns(&self) -> Vec<String> {
+		match self.columns.is_empty() {
+			true => vec![self.smiles_column.clone(), "ID".to_owned()],
+			false => self.columns.clone(),
+		}
+	}
+}
+
+/// Properties holding the commercial details of the compounds, all optional.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct OfferFields {
+	/// Price per mg, or the price of a pack when its quantity follows (e.g. "120/5mg", "95 per 1 g")
+	pub price_per_mg: Option<String>,
+	/// Pack sizes, separated by ',', ';' or '|'
+	pub pack_sizes: Option<String>,
+	/// Stock status (yes/no, true/false) or quantity
+	pub stock: Option<String>,
+	/// Delivery time in days, or in weeks when stated
+	pub lead_time_days: Option<String>,
+}
+
+/// A compound vendor (or an in-house collection) and how to read its catalog files.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProviderConfig {
+	pub name: String,
+	pub inputs: Vec<InputConfig>,
+	/// Properties holding the compound identifier, the first non-empty one is used
+	#[serde(default = "default_id_fields")]
+	pub id_fields: Vec<String>,
+	/// Prefixes removed from the identifiers (e.g. "MolPort-")
+	#[serde(default)]
+	pub id_strip_prefixes: Vec<String>,
+	/// Properties copied to the compound metadata
+	#[serde(default)]
+	pub metadata_fields: Vec<String>,
+	#[serde(default)]
+	pub offer_fields: OfferFields,
+	/// Store the vendor record (SD record with its properties, and SMILES) on each compound
+	#[serde(default)]
+	pub keep_original: bool,
+}
+
+impl ProviderConfig {
+	pub fn normalize_refid(&self, refid: String) -> String {
+		self.id_strip_prefixes
+			.iter()
+			.find_map(|prefix| refid.strip_prefix(prefix.as_str()))
+			.map(str::to_owned)
+			.unwrap_or(refid)
+	}
+}
+
+fn default_custom_profile_name() -> String {
+	"custom".to_owned()
+}
+
+/// Rules a building block has to satisfy to be imported, ranges are inclusive.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StandardizerProfile {
+	#[serde(default = "default_custom_profile_name")]
+	pub name: String,
+	pub allowed_atoms: Vec<String>,
+	pub hac: [u32; 2],
+	pub num_unspec_stereo: [u32; 2],
+	pub num_rot_bonds: [u32; 2],
+	pub num_rings: [u32; 2],
+	pub ring_size: [u32; 2],
+	/// Import each tautomer of a compound as a separate building block
+	pub enumerate_tautomers: bool,
+	/// Number of tautomers kept per compound, the compound itself included
+	pub max_tautomers: u32,
+}
+
+impl StandardizerProfile {
+	pub const PROFILE_NAMES: [&'static str; 2] = ["fragment-like", "lead-like"];
+
+	pub fn fragment_like() -> Self {
+		Self {
+			name: "fragment-like".to_owned(),
+			allowed_atoms: [
+					"H",
+					"C",
+					"N",
+					"O",
+					"P",
+					"S",
+
+					// Halogens
+					"F",
+					"Cl",
+					"Br",
+					"I",
+
+					// Others
+					"B",
+					"Sn",
+				]
+				.into_iter()
+				.map(str::to_owned)
+				.collect(),
+			hac: [5, 24],
+			num_unspec_stereo: [0, 1],
+			num_rot_bonds: [0, 16],
+			num_rings: [0, 3],
+			ring_size: [0, 7],
+			enumerate_tautomers: false,
+			max_tautomers: 8,
+		}
+	}
+
+	pub fn lead_like() -> Self {
+		Self {
+			name: "lead-like".to_owned(),
+			hac: [14, 32],
+			num_unspec_stereo: [0, 2],
+			num_rot_bonds: [0, 7],
+			num_rings: [0, 4],
+			..Self::fragment_like()
+		}
+	}
+
+	pub fn by_name(name: &str) -> Option<Self> {
+		match name {
+			"fragment-like" => Some(Self::fragment_like()),
+			"lead-like" => Some(Self::lead_like()),
+			_ => None,
+		}
+	}
+
+	pub fn range(bounds: [u32; 2]) -> RangeInclusive<u32> {
+		bounds[0]..=bounds[1]
+	}
+}
+
+// Custom profiles start from the fragment-like rules
+impl Default for StandardizerProfile {
+	fn default() -> Self {
+		Self {
+			name: default_custom_profile_name(),
+			..Self::fragment_like()
+		}
+	}
+}
+
+/// Either the name of a predefined profile or a custom profile.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum StandardizerConfig {
+	Named(String),
+	Custom(StandardizerProfile),
+}
+
+impl Default for StandardizerConfig {
+	fn default() -> Self {
+		Self::Named("fragment-like".to_owned())
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Config {
+	pub providers: Vec<ProviderConfig>,
+	#[serde(default)]
+	pub standardizer: StandardizerConfig,
+	/// JSONL file listing the compounds rejected by the import
+	#[serde(default = "default_import_report")]
+	pub import_report: String,
+}
+
+impl Config {
+	/// Reads the file named by CHEMODOTS_PROVIDERS_CONFIG, or providers.json in the working directory.
+	pub fn from_env() -> Result<Self, String> {
+		let path = std::env::var("CHEMODOTS_PROVIDERS_CONFIG")
+			.unwrap_or_else(|_| DEFAULT_PROVIDERS_CONFIG.to_owned());
+
+		let contents = fs::read(&path)
+			.map_err(|err| format!("Failed to read providers configuration {path}: {err}"))?;
+
+		let config: Self = serde_json::from_slice(&contents)
+			.map_err(|err| format!("Invalid providers configuration {path}: {err}"))?;
+
+		if let Some(provider) = config.providers.iter().find(|provider| provider.name.is_empty()) {
+			return Err(format!("Invalid providers configuration {path}: empty provider name ({provider:?})"));
+		}
+
+		if let Some(input) = config.providers.iter().flat_map(|provider| &provider.inputs).find(|input| input.delimiter.is_some_and(|delimiter| !delimiter.is_ascii())) {
+			return Err(format!("Invalid providers configuration {path}: non-ASCII delimiter for {}", input.path));
+		}
+
+		config.standardizer_profile()
+			.map_err(|err| format!("Invalid providers configuration {path}: {err}"))?;
+
+		Ok(config)
+	}
+
+	pub fn standardizer_profile(&self) -> Result<StandardizerProfile, String> {
+		let profile = match &self.standardizer {
+			StandardizerConfig::Named(name) => StandardizerProfile::by_name(name)
+				.ok_or_else(|| format!("unknown standardizer profile {name} (expected one of {})", StandardizerProfile::PROFILE_NAMES.join(", ")))?,
+			StandardizerConfig::Custom(profile) => profile.clone(),
+		};
+
+		let ranges = [
+			("hac", profile.hac),
+			("num_unspec_stereo", profile.num_unspec_stereo),
+			("num_rot_bonds", profile.num_rot_bonds),
+			("num_rings", profile.num_rings),
+			("ring_size", profile.ring_size),
+		];
+		if let Some((field, _)) = ranges.iter().find(|(_, bounds)| bounds[0] > bounds[1]) {
+			return Err(format!("empty {field} range in standardizer profile {}", profile.name));
+		}
+
+		if profile.max_tautomers == 0 {
+			return Err(format!("max_tautomers must be positive in standardizer profile {}", profile.name));
+		}
+
+		Ok(profile)
+	}
+}
@@ -0,0 +1,206 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use crossbeam::channel::Sender;
+use zip_next as zip;
+
+use crate::config::{InputCompression, InputConfig, InputFormat};
+
+/// A catalog entry as read from the file, the structure is parsed by the import workers.
+pub enum InputRecord {
+	/// The whole SD record (mol block, data items and terminator) and its data items
+	MolBlock(String, Vec<(String, String)>),
+	Smiles(String, Vec<(String, String)>),
+}
+
+impl InputRecord {
+	pub fn props(&self) -> &[(String, String)] {
+		match self {
+			Self::MolBlock(_, props) | Self::Smiles(_, props) => props,
+		}
+	}
+}
+
+/// The mol block of an SD record, up to its "M  END" line.
+pub fn mol_block(record: &str) -> &str {
+	let Some(start) = record.find("\nM  END") else {
+		return record;
+	};
+
+	match record[start + 1..].find('\n') {
+		Some(len) => &record[..start + 1 + len + 1],
+		None => record,
+	}
+}
+
+fn send(tx: &Sender<InputRecord>, record: InputRecord) -> io::Result<()> {
+	tx.send(record)
+		.map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+}
+
+/// Streams the records of an input to `tx`, decompressing it on the fly.
+pub fn read_records(input: &InputConfig, tx: &Sender<InputRecord>) -> io::Result<()> {
+	let file = File::open(&input.path)?;
+
+	match input.compression() {
+		InputCompression::None => read_stream(input, BufReader::new(file), tx),
+		InputCompression::Gzip => read_stream(input, BufReader::new(flate2::read::MultiGzDecoder::new(file)), tx),
+		InputCompression::Zstd => read_stream(input, BufReader::new(zstd::stream::read::Decoder::new(file)?), tx),
+		InputCompression::Zip => {
+			let mut file = BufReader::new(file);
+
+			// Entries are read in order, without extracting the archive
+			while let Some(entry) = zip::read::read_zipfile_from_stream(&mut file).map_err(io::Error::other)? {
+				if entry.is_dir() {
+					continue;
+				}
+
+				read_stream(input, BufReader::new(entry), tx)?;
+			}
+
+			Ok(())
+		},
+	}
+}
+
+fn read_stream(input: &InputConfig, reader: impl BufRead, tx: &Sender<InputRecord>) -> io::Result<()> {
+	match input.format() {
+		InputFormat::Sdf => read_sdf(reader, tx),
+		InputFormat::Smiles => read_delimited(input, reader, tx),
+	}
+}
+
+// Name of a data item header line, e.g. "> <ID>" or ">  25  <ID>  (MFCD0001)"
+fn parse_data_header(line: &str) -> Option<String> {
+	let line = line.strip_prefix('>')?;
+	let (_, name) = line.split_once('<')?;
+	let (name, _) = name.split_once('>')?;
+
+	Some(name.to_owned())
+}
+
+fn read_sdf(reader: impl BufRead, tx: &Sender<InputRecord>) -> io::Result<()> {
+	let mut record = String::new();
+	let mut props = vec![];
+	let mut prop: Option<(String, String)> = None;
+	let mut in_data = false;
+
+	for line in reader.lines() {
+		let line = line?;
+		let line = line.trim_end_matches('\r');
+
+		record.push_str(line);
+		record.push('\n');
+
+		if line == "$$$$" {
+			props.extend(prop.take());
+			send(tx, InputRecord::MolBlock(std::mem::take(&mut record), std::mem::take(&mut props)))?;
+			in_data = false;
+			continue;
+		}
+
+		if !in_data {
+			in_data = line.starts_with("M  END");
+			continue;
+		}
+
+		// Data items: a header, the value lines, then a blank line
+		if let Some(name) = parse_data_header(line) {
+			props.extend(prop.take());
+			prop = Some((name, String::new()));
+		} else if let Some((_, value)) = &mut prop {
+			if line.is_empty() {
+				props.extend(prop.take());
+			} else {
+				if !value.is_empty() {
+					value.push('\n');
+				}
+				value.push_str(line);
+			}
+		}
+	}
+
+	// The last record may lack its terminator
+	if !record.trim().is_empty() {
+		props.extend(prop.take());
+		send(tx, InputRecord::MolBlock(record, props))?;
+	}
+
+	Ok(())
+}
+
+fn read_delimited(input: &InputConfig, reader: impl BufRead, tx: &Sender<InputRecord>) -> io::Result<()> {
+	// The csv reader does not collapse repeated separators
+	if input.whitespace_delimited() {
+		return read_whitespace_delimited(input, reader, tx);
+	}
+
+	let mut reader = csv::ReaderBuilder::new()
+		.delimiter(input.delimiter())
+		.has_headers(input.header())
+		.flexible(true)
+		.trim(csv::Trim::All)
+		.from_reader(reader);
+
+	let columns = match input.header() && input.columns.is_empty() {
+		true => reader.headers()?.iter().map(str::to_owned).collect(),
+		false => input.columns(),
+	};
+
+	let idx_smiles = smiles_column_idx(input, &columns)?;
+
+	for record in reader.records() {
+		send_fields(tx, &columns, idx_smiles, record?.iter())?;
+	}
+
+	Ok(())
+}
+
+fn read_whitespace_delimited(input: &InputConfig, reader: impl BufRead, tx: &Sender<InputRecord>) -> io::Result<()> {
+	let mut lines = reader
+		.lines()
+		.filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()));
+
+	let header = match input.header() {
+		true => lines.next().transpose()?,
+		false => None,
+	};
+
+	let columns = match (header, input.columns.is_empty()) {
+		(Some(header), true) => header.split_whitespace().map(str::to_owned).collect(),
+		_ => input.columns(),
+	};
+
+	let idx_smiles = smiles_column_idx(input, &columns)?;
+
+	for line in lines {
+		send_fields(tx, &columns, idx_smiles, line?.split_whitespace())?;
+	}
+
+	Ok(())
+}
+
+fn smiles_column_idx(input: &InputConfig, columns: &[String]) -> io::Result<usize> {
+	columns
+		.iter()
+		.position(|column| column.eq_ignore_ascii_case(&input.smiles_column))
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Missing column {} in {}", input.smiles_column, input.path)))
+}
+
+fn send_fields<'a>(tx: &Sender<InputRecord>, columns: &[String], idx_smiles: usize, fields: impl Iterator<Item = &'a str>) -> io::Result<()> {
+	let fields = fields.collect::<Vec<_>>();
+
+	let Some(smiles) = fields.get(idx_smiles).filter(|smiles| !smiles.is_empty()) else {
+		return Ok(());
+	};
+
+	let props = columns
+		.iter()
+		.zip(&fields)
+		.enumerate()
+		.filter(|(idx, (_, value))| *idx != idx_smiles && !value.is_empty())
+		.map(|(_, (column, value))| (column.clone(), (*value).to_owned()))
+		.collect();
+
+	send(tx, InputRecord::Smiles((*smiles).to_owned(), props))
+}
@@ -1,10 +1,13 @@
+use std::collections::HashSet;
+use std::fs::File;
 use std::io::{self, Write};
 use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use chrono::Utc;
 use common::slugify;
-use crossbeam::channel::unbounded as mpsc;
-use db::model::{NewBuildingBlock, NewCompound, NewCompoundProvider, NewBuildingBlockReactant, NewMoiety, NewMoietyGroup, NewReaction, NewBuildingBlockOrigin};
+use crossbeam::channel::{bounded, unbounded as mpsc};
+use db::model::{CatalogSnapshot, NewCatalogSnapshot, NewCatalogSnapshotFile, NewBuildingBlock, NewCompound, NewCompoundProvider, NewBuildingBlockReactant, NewMoiety, NewMoietyGroup, NewReaction, NewReactionIncompatibleGroup, NewBuildingBlockOrigin};
 use field_count::FieldCount;
 use itertools::Itertools;
 use rayon::{prelude::*, ThreadPool};
@@ -18,10 +21,34 @@ use rdkit_rust::graphmol::romol::*;
 use rdkit_rust::graphmol::rwmol::*;
 use rdkit_rust::graphmol::substruct::substructmatch::*;
 use rdkit_rust::prelude::*;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 
 use chemodots_db as db;
 use chemodots_common as common;
 
+mod cli;
+mod config;
+mod input;
+mod offer;
+mod report;
+
+use cli::{Args, Command};
+use config::{Config, InputCompression, InputFormat, StandardizerProfile};
+use input::InputRecord;
+use offer::CompoundOffer;
+use report::{ImportRejection, ImportReport, ImportStage};
+
+/// Vendor data of a compound, stored along with it.
+#[derive(Clone, Debug)]
+struct CompoundDetails {
+	metadata: Option<Value>,
+	offer: CompoundOffer,
+	/// The record as supplied, for providers keeping it
+	sdf: Option<String>,
+	smiles: Option<String>,
+}
+
 #[derive(Debug)]
 pub enum StandardizeError {
 	ChooseLargestFragment,
@@ -54,40 +81,23 @@ pub struct ChemodotsStandardizer<'s> {
 	range_num_rot_bonds: RangeInclusive<u32>,
 	range_num_rings: RangeInclusive<u32>,
 	range_ring_size: RangeInclusive<u32>,
+	tautomer_enumerator: Option<InitializedHeap<'s, TautomerEnumerator>>,
+	max_tautomers: usize,
 }
 
 impl ChemodotsStandardizer<'_> {
-	pub fn new() -> Self {
-		let atom_symbols = [
-			"H",
-			"C",
-			"N",
-			"O",
-			"P",
-			"S",
-
-			// Halogens
-			"F",
-			"Cl",
-			"Br",
-			"I",
-
-			// Others
-			"B",
-			"Sn",
-			// "Si",
-		];
-		let atoms = atom_symbols
+	pub fn new(profile: &StandardizerProfile) -> Result<Self, String> {
+		let atoms = profile.allowed_atoms
 			.iter()
-			.map(|atom_symbol| Atom::new(&AtomInitParamsSymbol { symbol: atom_symbol }))
-			.filter_map(|atom| atom.ok())
-			.collect_vec();
+			.map(|atom_symbol| Atom::new(&AtomInitParamsSymbol { symbol: atom_symbol })
+				.map_err(|_| format!("Invalid allowed atom {atom_symbol} in standardizer profile {}", profile.name)))
+			.collect::<Result<Vec<_>, _>>()?;
 
 		let cleanup_params = new_local!(CleanupParameters);
 		let cleanup_params = cleanup_params.init(&CleanupParametersInitParams {
 				prefer_organic: Some(true),
 				do_canonical: Some(true),
-				max_tautomers: Some(1000),
+				max_tautomers: Some(profile.max_tautomers),
 				max_transforms: Some(1000),
 				tautomer_remove_sp3_stereo: Some(false),
 				tautomer_remove_bond_stereo: Some(true),
@@ -99,13 +109,19 @@ impl ChemodotsStandardizer<'_> {
 			})
 			.unwrap();
 
-		Self {
+		let tautomer_enumerator = profile.enumerate_tautomers
+			.then(|| TautomerEnumerator::new(&TautomerEnumeratorInitParams {
+					cleanup_params: &cleanup_params,
+				})
+				.unwrap());
+
+		Ok(Self {
 			largest_fragment_chooser: LargestFragmentChooser::new(&LargestFragmentChooserInitParams {
 					cleanup_params: &cleanup_params,
 				})
 				.unwrap(),
 			allowed_atoms: atoms,
-			range_hac: 5..=24,
+			range_hac: StandardizerProfile::range(profile.hac),
 			remove_hs_params: RemoveHsParameters {
 				remove_degree_zero: true,
 				remove_higher_degrees: false,
@@ -127,11 +143,38 @@ impl ChemodotsStandardizer<'_> {
 			},
 			uncharger: Uncharger::new(()).unwrap(),
 			reionizer: Reionizer::new(()).unwrap(),
-			range_num_unspec_stereo: 0..=1,
-			range_num_rot_bonds: 0..=16,
-			range_num_rings: 0..=3,
-			range_ring_size: 0..=7,
-		}
+			range_num_unspec_stereo: StandardizerProfile::range(profile.num_unspec_stereo),
+			range_num_rot_bonds: StandardizerProfile::range(profile.num_rot_bonds),
+			range_num_rings: StandardizerProfile::range(profile.num_rings),
+			range_ring_size: StandardizerProfile::range(profile.ring_size),
+			tautomer_enumerator,
+			max_tautomers: profile.max_tautomers as usize,
+		})
+	}
+
+	/// Distinct tautomers of a standardized molecule along with their SMILES, the molecule itself first.
+	pub fn tautomers<'s>(&self, smiles: String, mol: InitializedHeap<'s, ROMol>) -> Vec<(String, InitializedHeap<'s, ROMol>)> {
+		let Some(tautomer_enumerator) = &self.tautomer_enumerator else {
+			return vec![(smiles, mol)];
+		};
+
+		// Enumeration failures leave the molecule as standardized
+		let tautomers = tautomer_enumerator.enumerate(&mol)
+			.map(|res| (0..res.size())
+				.filter_map(|idx| res.get(idx).ok())
+				.filter_map(|tautomer| ROMol::new(ROMolInitParamsROMol {
+						romol: tautomer.get_ref(),
+					})
+					.ok())
+				.filter_map(|tautomer| Some((tautomer.to_smiles().ok()?, tautomer)))
+				.collect_vec())
+			.unwrap_or_default();
+
+		std::iter::once((smiles, mol))
+			.chain(tautomers)
+			.unique_by(|(smiles, _)| smiles.clone())
+			.take(self.max_tautomers)
+			.collect_vec()
 	}
 
 	pub fn standardize<'s>(&self, mol: InitializedLocal<'s, RWMol>) -> Result<InitializedLocal<'s, RWMol>, StandardizeError>
@@ -199,31 +242,40 @@ impl ChemodotsStandardizer<'_> {
 			Err(StandardizeError::ValidateNumRings)?;
 		}
 
-		// TODO: Ring size
+		let ring_info = mol.get_ring_info();
+		if ring_info.atom_rings().into_iter().any(|ring| !self.range_ring_size.contains(&(ring.len() as u32))) {
+			Err(StandardizeError::ValidateRingSize)?;
+		}
 
 		Ok(mol)
 	}
 }
 
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-enum Provider {
-	Enamine,
-	Molport,
-}
-
-fn boostrap(db_pool: &db::DBPool) -> Result<(), ()> {
+fn bootstrap(db_pool: &db::DBPool, config: &Config, dry_run: bool) -> Result<(), ()> {
 	let mut conn = db_pool.get().unwrap();
 
+	// Definitions are upserted by slug, running it again only applies their changes
+	db::transaction_or_dry_run(&mut conn, dry_run, |conn| {
+		bootstrap_definitions(conn, config);
+		Ok(())
+	}).unwrap();
+
+	Ok(())
+}
+
+fn bootstrap_definitions(conn: &mut db::model::DBConnection, config: &Config) {
 	// Moieties
 
 	println!("  Bootstrapping moieties...");
 
-	let ent_moiety_group = db::model::create_moiety_group(&mut conn, &NewMoietyGroup {
+	let ent_moiety_group = db::model::upsert_moiety_group(conn, &NewMoietyGroup {
 		name: "Primary arylamine",
+		slug: &slugify("Primary arylamine"),
 	}).unwrap();
 
-	db::model::create_moiety(&mut conn, &NewMoiety {
+	db::model::upsert_moiety(conn, &NewMoiety {
 		name: "Primary arylamine",
+		slug: &slugify("Primary arylamine"),
 		id_moiety_group: ent_moiety_group.id,
 		priority: 0,
 		rdpickle: &Vec::<u8>::new(),
@@ -322,6 +374,16 @@ fn boostrap(db_pool: &db::DBPool) -> Result<(), ()> {
 		// ("Hartenfeller 40: Mitsunobu tetrazole 4", "[C;H1&$(C([#6])[#6]),H2&$(C[#6]):1][OH1].[#7:2]1~[#7:3]~[#7H1:4]~[#7:5]~[#6:6]~1>>[#7:2]1:[#7:3]:[#7:4]([C:1]):[#7:5]:[#6:6]:1"),
 	];
 
+	// Reactions already bootstrapped, with their definition
+	let prev_reactions = db::model::get_reactions(conn)
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.map(|ent_reaction| (ent_reaction.slug, ent_reaction.smarts))
+		.collect::<std::collections::HashMap<_, _>>();
+
+	let mut new_count = 0;
+	let mut changed_count = 0;
+
 	react_defs
 		.into_iter()
 		.for_each(|(name, smarts)| {
@@ -336,55 +398,246 @@ fn boostrap(db_pool: &db::DBPool) -> Result<(), ()> {
 				.split_once(':')
 				.map(|(prefix, _)| prefix)
 				.unwrap_or(name));
+			// Hartenfeller et al., J. Chem. Inf. Model. 2011, 51, 3093-3098
+			let reference = name
+				.starts_with("Hartenfeller ")
+				.then_some("doi:10.1021/ci200379p");
 
-			db::model::create_reaction(&mut conn, &NewReaction {
+			let ent_reaction = db::model::upsert_reaction(conn, &NewReaction {
 				name,
 				slug,
 				smarts,
 				rdpickle: &pickle,
 				multistep: false,
-				reference: None,
+				reference,
 			}).unwrap();
+
+			// New reactions are pending until matched, changed ones must be matched again against every building block
+			match prev_reactions.get(slug) {
+				None => new_count += 1,
+				Some(prev_smarts) if prev_smarts != smarts => {
+					db::model::invalidate_reaction_reactants(conn, ent_reaction.id).unwrap();
+					changed_count += 1;
+				},
+				Some(_) => {},
+			}
+		});
+
+	println!("   {new_count} new and {changed_count} changed reactions.");
+
+	println!("   completed.");
+
+	// Incompatible groups
+
+	println!("  Bootstrapping reaction incompatible groups...");
+
+	const ALIPHATIC_AMINE: (&str, &str) = ("Aliphatic amine", "[NX3;H2,H1;!$(N-[C,S]=[O,S,N]);!$(N-a)]-[CX4]");
+	const HYDRAZINE: (&str, &str) = ("Hydrazine", "[NX3;H2]-[NX3]");
+	const THIOL: (&str, &str) = ("Thiol", "[SX2;H1]-[#6]");
+	const ALCOHOL: (&str, &str) = ("Aliphatic alcohol", "[OX2;H1]-[CX4]");
+	const ALDEHYDE: (&str, &str) = ("Aldehyde", "[CX3;H1](=O)-[#6]");
+	const ACYL_HALIDE: (&str, &str) = ("Acyl halide", "[CX3](=O)-[Cl,Br,I]");
+	const SULFONYL_HALIDE: (&str, &str) = ("Sulfonyl halide", "[SX4](=O)(=O)-[F,Cl,Br]");
+	const ISOCYANATE: (&str, &str) = ("Isocyanate", "[NX2]=C=[O,S]");
+	const ALKYL_HALIDE: (&str, &str) = ("Alkyl halide", "[CX4]-[Cl,Br,I]");
+
+	// Acylations and sulfonylations of an amine (the amine is always the second reactant)
+	let amide_slugs: &[&str] = &["hartenfeller_47", "hartenfeller_48", "hartenfeller_57", "iscb_68"];
+	let williamson_slugs: &[&str] = &["iscb_63", "iscb_64", "iscb_65"];
+
+	// (reactions, reactant index or any, group, excluded instead of protected)
+	let incompat_defs: [(&[&str], Option<i32>, (&str, &str), bool); 17] = [
+		(amide_slugs, Some(0), HYDRAZINE, false),
+		(amide_slugs, Some(0), ALCOHOL, false),
+		(amide_slugs, Some(0), THIOL, false),
+		(amide_slugs, Some(1), ACYL_HALIDE, true),
+		(amide_slugs, Some(1), SULFONYL_HALIDE, true),
+		(amide_slugs, Some(1), ISOCYANATE, true),
+		(amide_slugs, Some(1), ALDEHYDE, false),
+		(&["hartenfeller_30"], Some(0), HYDRAZINE, false),
+		(williamson_slugs, Some(0), ALIPHATIC_AMINE, false),
+		(williamson_slugs, Some(0), THIOL, false),
+		(williamson_slugs, Some(1), ALKYL_HALIDE, true),
+		(&["iscb_67"], Some(0), ALIPHATIC_AMINE, true),
+		(&["iscb_67"], Some(1), ALIPHATIC_AMINE, false),
+		(&["iscb_67"], Some(1), THIOL, false),
+		(&["iscb_70"], None, ALIPHATIC_AMINE, false),
+		(&["iscb_70"], None, THIOL, false),
+		(&["iscb_70"], Some(1), ACYL_HALIDE, true),
+	];
+
+	let reaction_ids = db::model::get_reactions(conn)
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.map(|ent_reaction| (ent_reaction.slug, ent_reaction.id))
+		.collect::<std::collections::HashMap<_, _>>();
+
+	// Groups already bootstrapped, by reaction, reactant and name, those left are no longer defined
+	let mut prev_groups = db::model::get_reaction_incompatible_groups(conn)
+		.unwrap()
+		.into_iter()
+		.map(|ent_group| ((ent_group.id_reaction, ent_group.reactant_idx, ent_group.name.clone()), ent_group))
+		.collect::<std::collections::HashMap<_, _>>();
+
+	// Reactions whose building blocks must be matched again
+	let mut changed_reaction_ids = HashSet::new();
+
+	incompat_defs
+		.into_iter()
+		.flat_map(|(slugs, reactant_idx, group, exclude)| slugs
+			.iter()
+			.filter_map(|slug| reaction_ids.get(*slug))
+			.map(move |id_reaction| (*id_reaction, reactant_idx, group, exclude)))
+		.for_each(|(id_reaction, reactant_idx, (name, smarts), exclude)| {
+			let prev_group = prev_groups.remove(&(id_reaction, reactant_idx, name.to_owned()));
+
+			match &prev_group {
+				Some(ent_group) if ent_group.smarts == smarts && ent_group.exclude == exclude => {},
+				_ => {
+					changed_reaction_ids.insert(id_reaction);
+				},
+			}
+
+			let query = new_local!(RWMol);
+			let query = query
+				.init(ParseSmartsParams {
+					text: smarts,
+					debug_parse: Default::default(),
+					merge_hs: Default::default(),
+					replacements: (),
+				})
+				.unwrap();
+			let pickle = query.to_pickle(Some(common::DEFAULT_MOL_PICKLE_OPTIONS)).unwrap();
+
+			let elem = NewReactionIncompatibleGroup {
+				id_reaction,
+				reactant_idx,
+				name,
+				rdpickle: &pickle,
+				smarts,
+				exclude,
+			};
+
+			match prev_group {
+				Some(ent_group) => db::model::update_reaction_incompatible_group(conn, ent_group.id, &elem),
+				None => db::model::create_reaction_incompatible_group(conn, &elem),
+			}.unwrap();
 		});
 
+	for ent_group in prev_groups.into_values() {
+		db::model::delete_reaction_incompatible_group(conn, ent_group.id).unwrap();
+		changed_reaction_ids.insert(ent_group.id_reaction);
+	}
+
+	for id_reaction in &changed_reaction_ids {
+		db::model::invalidate_reaction_reactants(conn, *id_reaction).unwrap();
+	}
+
+	println!("   {} reactions with changed incompatible groups.", changed_reaction_ids.len());
+
+	println!("   completed.");
+
 	// Providers
 
 	println!("  Bootstrapping providers...");
 
-	let ent_provider_enamine = db::model::get_compound_provider_by_name(&mut conn, "Enamine")
-		.or_else(|_| db::model::create_compound_provider(&mut conn, &NewCompoundProvider {
-			name: "Enamine",
-			ts_upd: Some(Utc::now().naive_utc())
-		})).unwrap();
-
-	let ent_provider_molport = db::model::get_compound_provider_by_name(&mut conn, "MolPort")
-		.or_else(|_| db::model::create_compound_provider(&mut conn, &NewCompoundProvider {
-			name: "MolPort",
-			ts_upd: Some(Utc::now().naive_utc())
-		})).unwrap();
+	for provider in &config.providers {
+		db::model::get_compound_provider_by_name(conn, &provider.name)
+			.or_else(|_| db::model::create_compound_provider(conn, &NewCompoundProvider {
+				name: &provider.name,
+				ts_upd: Some(Utc::now().naive_utc())
+			})).unwrap();
+	}
 
 	println!("   completed.");
+}
 
-	Ok(())
+fn record_catalog_snapshot(db_pool: &db::DBPool, config: &Config) -> Result<CatalogSnapshot, String> {
+	let mut conn = db_pool.get().unwrap();
+
+	// The rules the building blocks of this import were selected with
+	let standardizer_profile = serde_json::to_value(config.standardizer_profile().unwrap()).unwrap();
+
+	// Checksums of the vendor files, to tell which catalog release an experiment ran against
+	let files = config.providers
+		.iter()
+		.flat_map(|provider| {
+			let id_compound_provider = db::model::get_compound_provider_by_name(&mut conn, &provider.name).unwrap().id;
+
+			provider.inputs
+				.iter()
+				.map(move |input| (id_compound_provider, input.path.as_str()))
+		})
+		.collect_vec();
+
+	// Every file is read once here and again by the import, hashing costs little next to parsing the molecules.
+	// Done before the snapshot is recorded, so a missing file leaves no snapshot without its files.
+	let sha256s = files
+		.iter()
+		.map(|(_, filename)| {
+			let mut hasher = Sha256::new();
+			let mut file = File::open(filename)
+				.map_err(|err| format!("Cannot open the catalog file {filename}: {err}"))?;
+
+			io::copy(&mut file, &mut hasher)
+				.map_err(|err| format!("Cannot read the catalog file {filename}: {err}"))?;
+
+			Ok(const_hex::encode(hasher.finalize()))
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+
+	let snapshot = db::model::create_catalog_snapshot(&mut conn, &NewCatalogSnapshot {
+		ts: Utc::now().naive_utc(),
+		standardizer_profile: Some(&standardizer_profile),
+	}).unwrap();
+
+	let ent_files = files
+		.iter()
+		.zip(&sha256s)
+		.map(|((id_compound_provider, filename), sha256)| NewCatalogSnapshotFile {
+			id_catalog_snapshot: snapshot.id,
+			id_compound_provider: *id_compound_provider,
+			filename,
+			sha256,
+		})
+		.collect_vec();
+
+	db::model::create_catalog_snapshot_files(&mut conn, &ent_files).unwrap();
+
+	Ok(snapshot)
 }
 
-fn remove_prev_compounds(db_pool: &db::DBPool) -> Result<(), ()> {
+fn remove_prev_compounds(db_pool: &db::DBPool, snapshot: &CatalogSnapshot) -> Result<(), ()> {
 	let mut conn = db_pool.get().unwrap();
 
-	db::model::delete_all_building_block_origins(&mut conn).unwrap();
-	db::model::delete_all_compounds(&mut conn).unwrap();
+	// Previous experiments still refer to them, they are withdrawn instead of deleted
+	db::model::withdraw_all_compounds(&mut conn, snapshot).unwrap();
 
 	Ok(())
 }
 
-fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Result<(), ()> {
+/// Without a snapshot (dry run), the compounds are standardized and the report written, but nothing is stored.
+fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool, config: &Config, snapshot: Option<&CatalogSnapshot>) -> Result<(), ()> {
 	let mut conn = db_pool.get().unwrap();
 	let cpu_cnt = num_cpus::get();
 
-	let standardizer = ChemodotsStandardizer::new();
+	let standardizer = ChemodotsStandardizer::new(&config.standardizer_profile().unwrap()).unwrap();
 
-	let ent_provider_enamine = db::model::get_compound_provider_by_name(&mut conn, "Enamine").unwrap();
-	let ent_provider_molport = db::model::get_compound_provider_by_name(&mut conn, "MolPort").unwrap();
+	// Providers are referred to by their index in the configuration, a dry run may precede their bootstrap
+	let providers = &config.providers;
+	let provider_ids = match snapshot {
+		Some(_) => providers
+			.iter()
+			.map(|provider| db::model::get_compound_provider_by_name(&mut conn, &provider.name).unwrap().id)
+			.collect_vec(),
+		None => vec![],
+	};
+
+	let counter_new_compounds = AtomicUsize::new(0);
+	let counter_changed_compounds = AtomicUsize::new(0);
+
+	let import_report = ImportReport::create(&config.import_report).unwrap();
+	let report = &import_report;
 
 	thread_pool.in_place_scope(|scope| {
 		let (tx, rx) = mpsc();
@@ -394,85 +647,78 @@ fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Resul
 		scope.spawn(move |_scope| {
 			println!("  Fetching and standardizing the compounds...");
 
-			let suppl_molport = new_local!(MultithreadedSDMolSupplier);
-			let mut suppl_molport = suppl_molport.init(MultithreadedSDMolSupplierInitParamsFilenameEx {
-					filename: "in-molport.sdf",
-					sanitize: Some(false),
-					remove_hs: Some(false),
-					strict_parsing: Some(true),
-					num_writer_threads: Some(cpu_cnt),
-					size_input_queue: Some(128 * cpu_cnt),
-					size_output_queue: Some(128 * cpu_cnt),
-				})
-				.unwrap();
-
-			let suppl_enamine = new_local!(MultithreadedSDMolSupplier);
-			let mut suppl_enamine = suppl_enamine.init(MultithreadedSDMolSupplierInitParamsFilenameEx {
-					filename: "in-enamine.sdf",
-					sanitize: Some(false),
-					remove_hs: Some(false),
-					strict_parsing: Some(true),
-					num_writer_threads: Some(cpu_cnt),
-					size_input_queue: Some(128 * cpu_cnt),
-					size_output_queue: Some(128 * cpu_cnt),
-				})
-				.unwrap();
-
-			suppl_molport
-				.par_bridge()
-				.map(|e| (Provider::Molport, e))
-				.chain(suppl_enamine
-					.par_bridge()
-					.map(|e| (Provider::Enamine, e)))
-				.map(|(provider, mol)| -> Result<_, String> {
+			for (idx_provider, provider) in providers.iter().enumerate() {
+				// The record as supplied is only available when streamed, see below
+				let standardize_compound = |suppl_mol: &InitializedHeap<ROMol>, original: Option<&InputRecord>| -> Result<_, ImportRejection> {
 					let rwmol = new_local!(RWMol);
 					let mut mol = rwmol
 						.init(RWMolInitParamsROMol {
-							romol: &mol,
+							romol: suppl_mol,
 						})
 						.unwrap();
 
-					let mut refid = mol
-						.get_prop_str("PUBCHEM_EXT_DATASOURCE_REGID")
-						.filter(|refid| !refid.is_empty())
-						.or_else(|| mol
-							.get_prop_str("ID")
-							.filter(|refid| !refid.is_empty()))
-						.or_else(|| mol
-							.get_prop_str("Id")
-							.filter(|refid| !refid.is_empty()))
-						.or_else(|| mol
-							.get_prop_str("id")
-							.filter(|refid| !refid.is_empty()))
-						.ok_or(format!("Compound {provider:?}<unknown>: Failed to get compound name"))?;
+					let reject = |refid: Option<&str>, stage, reason: &str, message: &str| ImportRejection {
+						provider: provider.name.clone(),
+						refid: refid.map(str::to_owned),
+						smiles: suppl_mol.to_smiles().ok(),
+						stage,
+						reason: reason.to_owned(),
+						message: message.to_owned(),
+					};
 
-					match provider {
-						Provider::Molport => {
-							if let Some(stripped) = refid.strip_prefix("MolPort-") {
-								refid = stripped.to_owned();
-							}
+					let refid = provider.id_fields
+						.iter()
+						.find_map(|field| mol
+							.get_prop_str(field)
+							.filter(|refid| !refid.is_empty()))
+						.ok_or_else(|| reject(None, ImportStage::Identifier, "MissingIdentifier", "Failed to get compound name"))?;
+
+					let refid = provider.normalize_refid(refid);
+
+					let metadata = provider.metadata_fields
+						.iter()
+						.filter_map(|field| mol
+							.get_prop_str(field)
+							.map(|value| (field.clone(), Value::String(value))))
+						.collect::<serde_json::Map<_, _>>();
+					let metadata = (!metadata.is_empty()).then_some(Value::Object(metadata));
+
+					let offer = CompoundOffer::from_props(&provider.offer_fields, |field| mol.get_prop_str(field));
+
+					let details = CompoundDetails {
+						metadata,
+						offer,
+						sdf: match original {
+							Some(InputRecord::MolBlock(record, _)) => Some(record.clone()),
+							_ => None,
 						},
-						_ => {},
-					}
+						smiles: match original {
+							Some(InputRecord::Smiles(text, _)) => Some(text.clone()),
+							_ => None,
+						},
+					};
 
 					mol.set_prop_str("_Name", &refid);
 
 					let mol = standardizer
 						.standardize(mol)
-						.map_err(|err| match err {
-							StandardizeError::ChooseLargestFragment => "Failed to choose the largest fragment",
-							StandardizeError::ValidateAllowedAtoms => "Forbidden atoms found",
-							StandardizeError::ValidateHAC => "Heavy atoms count out of bounds",
-							StandardizeError::RemoveHs => "Failed to remove hydrogens",
-							StandardizeError::Uncharge => "Failed to uncharge",
-							StandardizeError::Reionize => "Failed to reionize",
-							StandardizeError::AssignStereo => "Failed to assign stereochemistry",
-							StandardizeError::ValidateNumUnspecStereo => "Unspecified stereo centers count out of bounds",
-							StandardizeError::ValidateNumRotatableBonds => "Rotatable bonds count out of bounds",
-							StandardizeError::ValidateNumRings => "Rings (SSSR) count out of bounds",
-							StandardizeError::ValidateRingSize => "At least one ring size out of bounds",
-						})
-						.map_err(|msg| format!("Compound {provider:?}.{refid}: {msg}"))?;
+						.map_err(|err| {
+							let msg = match err {
+								StandardizeError::ChooseLargestFragment => "Failed to choose the largest fragment",
+								StandardizeError::ValidateAllowedAtoms => "Forbidden atoms found",
+								StandardizeError::ValidateHAC => "Heavy atoms count out of bounds",
+								StandardizeError::RemoveHs => "Failed to remove hydrogens",
+								StandardizeError::Uncharge => "Failed to uncharge",
+								StandardizeError::Reionize => "Failed to reionize",
+								StandardizeError::AssignStereo => "Failed to assign stereochemistry",
+								StandardizeError::ValidateNumUnspecStereo => "Unspecified stereo centers count out of bounds",
+								StandardizeError::ValidateNumRotatableBonds => "Rotatable bonds count out of bounds",
+								StandardizeError::ValidateNumRings => "Rings (SSSR) count out of bounds",
+								StandardizeError::ValidateRingSize => "At least one ring size out of bounds",
+							};
+
+							reject(Some(&refid), ImportStage::Standardize, &err.to_string(), msg)
+					})?;
 
 					let mol = ROMol::new(ROMolInitParamsROMol {
 							romol: &mol,
@@ -481,19 +727,115 @@ fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Resul
 
 					let smiles = mol
 						.to_smiles()
-						.map_err(|_| format!("Compound {provider:?}.{refid}: Failed to generate SMILES"))?;
-
-					Ok(((provider, refid), (smiles, mol)))
-				})
-				.filter_map(|e| {
-					if let Err(err) = &e {
-						eprintln!("{err}");
+						.map_err(|_| reject(Some(&refid), ImportStage::Smiles, "ToSmiles", "Failed to generate SMILES"))?;
+
+					let tautomers = standardizer.tautomers(smiles.clone(), mol);
+
+					Ok(((idx_provider, refid), (smiles, tautomers, details)))
+				};
+
+				for input in &provider.inputs {
+					println!("   {}...", input.path);
+
+					// Plain SD files are parsed by RDKit directly, unless their records are kept as supplied
+					if input.format() == InputFormat::Sdf && input.compression() == InputCompression::None && !provider.keep_original {
+						let suppl = new_local!(MultithreadedSDMolSupplier);
+						let mut suppl = suppl.init(MultithreadedSDMolSupplierInitParamsFilenameEx {
+								filename: &input.path,
+								sanitize: Some(false),
+								remove_hs: Some(false),
+								strict_parsing: Some(true),
+								num_writer_threads: Some(cpu_cnt),
+								size_input_queue: Some(128 * cpu_cnt),
+								size_output_queue: Some(128 * cpu_cnt),
+							})
+							.unwrap();
+
+						suppl
+							.par_bridge()
+							.map(|suppl_mol| standardize_compound(&suppl_mol, None))
+							.filter_map(|e| e
+								.map_err(|rejection| report.reject(rejection))
+								.ok())
+							.for_each_with(tx.clone(), |s, elem| {
+								s.send(elem).unwrap();
+							});
+
+						continue;
 					}
-					e.ok()
-				})
-				.for_each_with(tx, |s, elem| {
-					s.send(elem).unwrap();
-				});
+
+					let (tx_records, rx_records) = bounded(128 * cpu_cnt);
+
+					std::thread::scope(|thread_scope| {
+						let reader = thread_scope.spawn(move || input::read_records(input, &tx_records));
+
+						rx_records
+							.into_iter()
+							.par_bridge()
+							.map(|record| -> Result<_, ImportRejection> {
+								let refid = provider.id_fields
+									.iter()
+									.find_map(|field| record
+										.props()
+										.iter()
+										.find(|(name, value)| name == field && !value.is_empty())
+										.map(|(_, value)| provider.normalize_refid(value.clone())));
+
+								let rwmol = new_local!(RWMol);
+								let (mol, smiles) = match &record {
+									InputRecord::MolBlock(record, _) => (rwmol
+										.init(ParseMolBlockParams {
+											mol_block: input::mol_block(record),
+											sanitize: Some(false),
+											remove_hs: Some(false),
+											strict_parsing: Some(true),
+										}), None),
+									InputRecord::Smiles(text, _) => (rwmol
+										.init(ParseSmilesParams {
+											text,
+											debug_parse: Default::default(),
+											sanitize: Some(false),
+											replacements: (),
+										}), Some(text.clone())),
+								};
+
+								let mut mol = mol
+									.map_err(|_| ImportRejection {
+										provider: provider.name.clone(),
+										refid,
+										smiles,
+										stage: ImportStage::Parse,
+										reason: "ParseStructure".to_owned(),
+										message: "Failed to parse the structure".to_owned(),
+									})?;
+
+								for (name, value) in record.props() {
+									mol.set_prop_str(name, value);
+								}
+
+								let suppl_mol = ROMol::new(ROMolInitParamsROMol {
+										romol: &mol,
+									})
+									.unwrap();
+
+								standardize_compound(&suppl_mol, provider.keep_original.then_some(&record))
+							})
+							.filter_map(|e| e
+								.map_err(|rejection| report.reject(rejection))
+								.ok())
+							.for_each_with(tx.clone(), |s, elem| {
+								s.send(elem).unwrap();
+							});
+
+						// Unreadable inputs are a hard error
+						reader
+							.join()
+							.unwrap()
+							.map_err(|err| format!("Failed to read {}: {err}", input.path))
+							.unwrap();
+					});
+				}
+			}
 
 			println!("   completed.");
 		});
@@ -511,31 +853,47 @@ fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Resul
 
 			grouped_refid
 				.par_drain()
-				.filter_map(|((provider, refid), v)| {
+				.filter_map(|((idx_provider, refid), v)| {
+					let provider_name = &providers[idx_provider].name;
+
 					let unique_count = match v.len() {
 						len if len <= 1 => len,
 						_ => v
 							.iter()
-							.unique_by(|(smiles, _)| smiles)
+							.unique_by(|(smiles, _, _)| smiles)
 							.count(),
 					};
 
 					let res = match unique_count {
-						0 => Err(format!("Compound {provider:?}.{refid}: Missing smiles.")),
+						0 => Err(("MissingSmiles", "Missing smiles.".to_owned())),
 						1 => {
-							let (smiles, mol) = v.into_iter().next().unwrap();
-							Ok((smiles, (provider, refid, mol)))
+							let (_, tautomers, details) = v.into_iter().next().unwrap();
+
+							// Each tautomer is a building block of its own
+							return Some(tautomers
+								.into_iter()
+								.map(|(smiles, mol)| (smiles, (idx_provider, refid.clone(), mol, details.clone())))
+								.collect_vec());
 						},
-						cnt => Err(format!("Compound {provider:?}.{refid}: {cnt} conflicting entries.")),
+						cnt => Err(("ConflictingEntries", format!("{cnt} conflicting entries."))),
 					};
 
-					if let Err(err) = &res {
-						eprintln!("{err}");
-					}
-					res.ok()
+					res
+						.map_err(|(reason, message)| report.reject(ImportRejection {
+							provider: provider_name.clone(),
+							refid: Some(refid),
+							// The standardized structures of the conflicting entries
+							smiles: Some(v.iter().map(|(smiles, _, _)| smiles).unique().join(" ")),
+							stage: ImportStage::DuplicateId,
+							reason: reason.to_owned(),
+							message,
+						}))
+						.ok()
 				})
-				.for_each_with(tx, |s, elem| {
-					s.send(elem).unwrap();
+				.for_each_with(tx, |s, elems| {
+					for elem in elems {
+						s.send(elem).unwrap();
+					}
 				});
 
 				println!("   completed.");
@@ -551,111 +909,202 @@ fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Resul
 
 		let infos = grouped_smiles
 			.par_drain()
-			.map(|e: (String, Vec<(Provider, String, InitializedHeap<ROMol>)>)| -> Result<_, &str> {
+			.map(|e: (String, Vec<(usize, String, InitializedHeap<ROMol>, CompoundDetails)>)| -> Result<_, ()> {
 				let (smiles, v) = e;
-				let (_, _, mol0) = &v[0];
+				let (_, _, mol0, _) = &v[0];
 
 				let rdpickle = mol0
 					.to_pickle(Some(common::DEFAULT_MOL_PICKLE_OPTIONS))
-					.map_err(|_| "Failed to generate pickle")?;
+					.map_err(|_| v
+						.iter()
+						.for_each(|(idx_provider, refid, _, _)| report.reject(ImportRejection {
+							provider: providers[*idx_provider].name.clone(),
+							refid: Some(refid.clone()),
+							smiles: Some(smiles.clone()),
+							stage: ImportStage::BuildingBlock,
+							reason: "ToPickle".to_owned(),
+							message: "Failed to generate pickle".to_owned(),
+						})))?;
+
+				let scaffolds = common::scaffold::murcko_scaffolds(mol0);
+				let identifiers = common::identifier::inchi_identifiers(mol0);
 
 				let compound_refs = v
 					.into_iter()
-					.map(|(provider, refid, _)| (provider, refid))
+					.map(|(idx_provider, refid, _, details)| (idx_provider, refid, details))
 					.collect_vec();
 
-				Ok((smiles, rdpickle, compound_refs))
+				Ok((smiles, rdpickle, scaffolds, identifiers, compound_refs))
 			})
 			.filter_map(|e| e.ok())
 			.collect::<Vec<_>>();
 
 		println!("   completed.");
 
+		let Some(snapshot) = snapshot else {
+			infos
+				.iter()
+				.flat_map(|(_, _, _, _, compound_refs)| compound_refs)
+				.map(|(idx_provider, refid, _)| (idx_provider, refid))
+				.unique()
+				.for_each(|(idx_provider, _)| report.accept(&providers[*idx_provider].name));
+
+			println!("  {} building blocks (dry run, not inserted).", infos.len());
+
+			return;
+		};
+
+		// Compounds of these providers not seen since the snapshot are withdrawn
+		let ts_import = snapshot.ts;
+
 		println!("  Inserting building blocks...");
 
-		infos
+		let building_block_refs = infos
 			.par_chunks(infos.len().div_ceil(cpu_cnt)
-				.min(65535 / NewBuildingBlock::field_count()))
-			.try_for_each(|e: &[(String, Vec<u8>, Vec<(Provider, String)>)]| -> Result<_, String> {
+				.clamp(1, 65535 / NewBuildingBlock::field_count()))
+			.map(|e: &[(String, Vec<u8>, Option<common::scaffold::Scaffolds>, Option<common::identifier::Identifiers>, Vec<(usize, String, CompoundDetails)>)]| -> Result<_, String> {
 				let mut conn = db_pool.get().unwrap();
 
 				let ent_building_blocks = e
 					.into_iter()
-					.map(|(smiles, rdpickle, _)| NewBuildingBlock {
+					.map(|(smiles, rdpickle, scaffolds, identifiers, _)| NewBuildingBlock {
 						smiles,
 						rdpickle,
+						scaffold_smiles: scaffolds.as_ref().map(|e| e.scaffold.as_str()),
+						generic_scaffold_smiles: scaffolds.as_ref().map(|e| e.generic.as_str()),
+						inchi: identifiers.as_ref().map(|e| e.inchi.as_str()),
+						inchikey: identifiers.as_ref().map(|e| e.inchikey.as_str()),
 					})
 					.collect_vec();
 
 				let ent_building_blocks = db::model::get_or_create_building_blocks(&mut conn, &ent_building_blocks)
 					.map_err(|err| format!("Failed to insert building blocks: {err:?}"))?;
 
-				let chunks = e
+				Ok(ent_building_blocks
 					.into_iter()
-					.enumerate()
-					.flat_map(|(idx_bbs, e)| {
-						let (_, _, compound_refs) = e;
+					.zip(e)
+					.flat_map(|(ent_building_block, (_, _, _, _, compound_refs))| compound_refs
+						.iter()
+						.map(move |(idx_provider, refid, details)| ((*idx_provider, refid), (ent_building_block.id, details))))
+					.collect_vec())
+			})
+			.collect::<Result<Vec<_>, String>>()
+			// Failure to insert is a hard error
+			.unwrap();
 
-						let ent_provider_enamine = &ent_provider_enamine;
-						let ent_provider_molport = &ent_provider_molport;
+		println!("   completed.");
 
-						compound_refs
-							.into_iter()
-							.map(move |(provider, refid)| {
-								let id_provider = match provider {
-									Provider::Enamine => ent_provider_enamine.id,
-									Provider::Molport => ent_provider_molport.id,
-								};
+		println!("  Inserting compounds...");
 
-								(
-									idx_bbs,
-									NewCompound {
-										id_compound_provider: id_provider,
-										refid,
-										sdf: None,
-										smiles: None,
-										available: true,
-									},
-								)
-							})
+		// A compound leads to several building blocks when its tautomers are enumerated
+		let compound_building_blocks = building_block_refs
+			.into_iter()
+			.flatten()
+			.into_group_map()
+			.into_iter()
+			.collect_vec();
+
+		compound_building_blocks
+			.par_chunks(compound_building_blocks.len().div_ceil(cpu_cnt)
+				.clamp(1, 65535 / NewCompound::field_count()))
+			.try_for_each(|chunk| -> Result<(), String> {
+				let mut conn = db_pool.get().unwrap();
+
+				let ent_compounds = chunk
+					.iter()
+					.map(|((idx_provider, refid), building_blocks)| {
+						report.accept(&providers[*idx_provider].name);
+
+						let (_, details) = building_blocks[0];
+
+						NewCompound {
+							id_compound_provider: provider_ids[*idx_provider],
+							refid,
+							sdf: details.sdf.as_deref(),
+							smiles: details.smiles.as_deref(),
+							available: true,
+							metadata: details.metadata.as_ref(),
+							ts_seen: Some(ts_import),
+							price_per_mg: details.offer.price_per_mg,
+							pack_sizes: details.offer.pack_sizes.as_deref(),
+							in_stock: details.offer.in_stock,
+							lead_time_days: details.offer.lead_time_days,
+						}
 					})
-					.chunks(65535 / NewCompound::field_count());
+					.collect_vec();
 
-				chunks
-					.into_iter()
-					.try_for_each(|chunk| -> Result<(), String> {
-						let mut conn = db_pool.get().unwrap();
+				let ent_compounds = db::model::upsert_compounds_return(&mut conn, &ent_compounds)
+					.map_err(|err| format!("Failed to insert compounds: {err:?}"))?;
 
-						let (idx_bbs, ent_compounds): (Vec<_>, Vec<_>) = chunk.unzip();
+				let compound_ids = ent_compounds
+					.iter()
+					.map(|ent_compound| ent_compound.id)
+					.collect_vec();
 
-						let ent_compounds = db::model::create_compounds_return(&mut conn, &ent_compounds)
-							.map_err(|err| format!("Failed to insert compounds: {err:?}"))?;
+				let prev_origins = db::model::get_building_block_origins_with_compounds(&mut conn, &compound_ids)
+					.map_err(|err| format!("Failed to fetch building block origins: {err:?}"))?;
+
+				let origins: HashSet<_> = chunk
+					.iter()
+					.zip(&ent_compounds)
+					.flat_map(|((_, building_blocks), ent_compound)| building_blocks
+						.iter()
+						.map(|(id_building_block, _)| (*id_building_block, ent_compound.id)))
+					.collect();
+
+				// The structure of these compounds changed, they now lead to other building blocks
+				let stale_origins = prev_origins
+					.iter()
+					.filter(|ent_origin| !origins.contains(&(ent_origin.id_building_block, ent_origin.id_compound)))
+					.collect_vec();
 
-						let ent_building_block_origins = idx_bbs
-							.into_iter()
-							.zip(ent_compounds)
-							.map(|(idx_bb, ent_compound)| NewBuildingBlockOrigin {
-								id_building_block: ent_building_blocks[idx_bb].id,
-								id_compound: ent_compound.id,
-							})
-							.collect_vec();
+				let stale_origin_ids = stale_origins
+					.iter()
+					.map(|ent_origin| ent_origin.id)
+					.collect_vec();
 
-						let chunks = ent_building_block_origins
-							.chunks(65535 / NewBuildingBlockOrigin::field_count());
+				db::model::close_building_block_origins(&mut conn, &stale_origin_ids, snapshot)
+					.map_err(|err| format!("Failed to close building block origins: {err:?}"))?;
+
+				let prev_compound_ids: HashSet<_> = prev_origins
+					.iter()
+					.map(|ent_origin| ent_origin.id_compound)
+					.collect();
+				let changed_compound_ids: HashSet<_> = stale_origins
+					.iter()
+					.map(|ent_origin| ent_origin.id_compound)
+					.collect();
+				let prev_origins: HashSet<_> = prev_origins
+					.iter()
+					.map(|ent_origin| (ent_origin.id_building_block, ent_origin.id_compound))
+					.collect();
+
+				counter_new_compounds.fetch_add(compound_ids.len() - prev_compound_ids.len(), Ordering::Relaxed);
+				counter_changed_compounds.fetch_add(changed_compound_ids.len(), Ordering::Relaxed);
+
+				let ent_building_block_origins = origins
+					.into_iter()
+					.filter(|origin| !prev_origins.contains(origin))
+					.map(|(id_building_block, id_compound)| NewBuildingBlockOrigin {
+						id_building_block,
+						id_compound,
+						id_catalog_snapshot_from: snapshot.id,
+					})
+					.collect_vec();
 
-						chunks
-							.into_iter()
-							.try_for_each(|chunk| -> Result<(), String> {
-								db::model::create_building_block_origins(&mut conn, &chunk)
-									.map_err(|err| format!("Failed to insert building block origins: {err:?}"))?;
+				let chunks = ent_building_block_origins
+					.chunks(65535 / NewBuildingBlockOrigin::field_count());
 
-								Ok(())
-							})?;
+				chunks
+					.into_iter()
+					.try_for_each(|chunk| -> Result<(), String> {
+						db::model::create_building_block_origins(&mut conn, &chunk)
+							.map_err(|err| format!("Failed to insert building block origins: {err:?}"))?;
 
 						Ok(())
 					})?;
 
-					Ok(())
+				Ok(())
 			})
 			// Failure to insert is a hard error
 			.unwrap();
@@ -663,13 +1112,80 @@ fn import_new_compounds(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Resul
 		println!("   completed.");
 	});
 
+	if let Some(snapshot) = snapshot {
+		println!("  Withdrawing compounds missing from the import...");
+
+		let withdrawn_count = db::model::withdraw_compounds_unseen_since(&mut conn, &provider_ids, snapshot).unwrap();
+
+		println!("   completed.");
+
+		println!("  {} new, {} changed and {withdrawn_count} withdrawn compounds.",
+			counter_new_compounds.load(Ordering::Relaxed),
+			counter_changed_compounds.load(Ordering::Relaxed));
+	}
+
+	println!("  Writing import report...");
+
+	let summary = import_report.finish().unwrap();
+
+	for (provider, counts) in summary["providers"].as_object().unwrap() {
+		println!("   {provider}: {} accepted, {} rejected", counts["accepted"], counts["rejected"]);
+
+		for reason in counts["reasons"].as_array().unwrap() {
+			println!("     {} {}: {}", reason["stage"].as_str().unwrap(), reason["reason"].as_str().unwrap(), reason["count"]);
+		}
+	}
+
+	if let Some(snapshot) = snapshot {
+		db::model::update_catalog_snapshot_import_summary(&mut conn, snapshot.id, &summary).unwrap();
+	}
+
+	println!("   completed.");
+
 	Ok(())
 }
 
-fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPool) -> Result<(), ()> {
+fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPool, dry_run: bool) -> Result<(), ()> {
 	let mut conn = db_pool.get().unwrap();
 	let cpu_cnt = num_cpus::get();
 
+	// New or changed reactions, every building block must be matched against them again
+	let stale_reaction_ids = db::model::get_reactions(&mut conn)
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.filter(|ent_reaction| ent_reaction.ts_reactants.is_none())
+		.map(|ent_reaction| ent_reaction.id)
+		.collect::<HashSet<_>>();
+
+	// A dry run only tells how many building blocks would be matched
+	if dry_run {
+		println!("  {} building blocks to match.", db::model::count_building_blocks_pending_reactants(&mut conn).unwrap());
+		println!("  {} reactions to match against every building block.", stale_reaction_ids.len());
+
+		return Ok(());
+	}
+
+	if !stale_reaction_ids.is_empty() {
+		let (deleted_count, obsolete_count) = db::model::delete_building_block_reactants_with_reactions(&mut conn, &stale_reaction_ids.iter().copied().collect_vec())
+			.unwrap();
+
+		println!("  {} reactions to match against every building block, {deleted_count} previous matches removed and {obsolete_count} kept as obsolete for the experiments.", stale_reaction_ids.len());
+	}
+
+	// The building blocks never matched against the reactions, and every one of them when a reaction must be matched again
+	let ent_building_blocks = match stale_reaction_ids.is_empty() {
+			true => db::model::get_building_blocks_pending_reactants(&mut conn).unwrap().filter_map(|x| x.ok()).collect_vec(),
+			false => db::model::get_building_blocks(&mut conn).unwrap().filter_map(|x| x.ok()).collect_vec(),
+		};
+
+	let building_block_ids = ent_building_blocks
+		.iter()
+		.filter(|ent_building_block| ent_building_block.ts_reactants.is_none())
+		.map(|ent_building_block| ent_building_block.id)
+		.collect_vec();
+
+	println!("  {} building blocks to match.", building_block_ids.len());
+
 	thread_pool.in_place_scope(|scope| {
 		let (tx, rx) = mpsc();
 
@@ -687,11 +1203,23 @@ fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPo
 			})
 			.collect_vec();
 
+		let incompatible_groups = db::model::get_reaction_incompatible_groups(&mut conn)
+			.unwrap()
+			.into_iter()
+			.map(|ent_group| {
+				let query = ROMol::new(ROMolFromPickleParams {
+						pickle: &ent_group.rdpickle
+					})
+					.unwrap();
+
+				(ent_group.id_reaction, (ent_group.reactant_idx, ent_group.name, ent_group.exclude, query))
+			})
+			.into_group_map();
+
+		let stale_reaction_ids = &stale_reaction_ids;
+
 		scope.spawn(move |_scope| {
-			db::model::get_building_blocks(&mut conn)
-				.unwrap()
-				.filter_map(|x| x.ok())
-				.collect_vec()
+			ent_building_blocks
 				.into_par_iter()
 				.for_each(|ent_building_block| {
 					let building_block = new_local!(ROMol);
@@ -701,6 +1229,13 @@ fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPo
 						.unwrap();
 
 					let reactions = &reactions;
+					let incompatible_groups = &incompatible_groups;
+
+					// Building blocks already matched only need the reactions that changed since
+					let reactions = reactions
+						.iter()
+						.filter(|(id_reaction, _)| ent_building_block.ts_reactants.is_none() || stale_reaction_ids.contains(id_reaction));
+
 					for (id_reaction, reaction) in reactions {
 						let reactants = reaction.get_reactants();
 						let reactant_count = reactants.size();
@@ -723,10 +1258,36 @@ fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPo
 							.exactly_one();
 
 						if let Ok(reactant_idx) = reactant_idx {
+							let reactant_idx: i32 = reactant_idx.try_into().unwrap();
+
+							// Other groups of the building block which would react too: they must be protected,
+							// or the building block discarded for this reaction when no protection is practical.
+
+							let found_groups = incompatible_groups
+								.get(id_reaction)
+								.into_iter()
+								.flatten()
+								.filter(|(group_reactant_idx, _, _, _)| group_reactant_idx.map_or(true, |idx| idx == reactant_idx))
+								.filter(|(_, _, _, query)| {
+									let matches = new_local!(MatchVectTypeVec);
+									matches
+										.init(&MatchVectTypeVecInitParamsFromSubstructMatch::new(&building_block, query))
+										.is_ok()
+								})
+								.collect_vec();
+
 							let bbr = NewBuildingBlockReactant {
 								id_reaction: *id_reaction,
 								id_building_block: ent_building_block.id,
-								reactant_idx: reactant_idx.try_into().unwrap(),
+								reactant_idx,
+								incompatible_groups: (!found_groups.is_empty()).then(|| found_groups
+									.iter()
+									.map(|(_, name, _, _)| name)
+									.unique()
+									.join(",")),
+								excluded: found_groups
+									.iter()
+									.any(|(_, _, exclude, _)| *exclude),
 							};
 
 							tx.send(bbr).unwrap();
@@ -743,18 +1304,81 @@ fn compute_building_block_reactants(db_pool: &db::DBPool, thread_pool: &ThreadPo
 		bbrs
 			.par_chunks(bbrs.len()
 				.div_ceil(cpu_cnt)
-				.min(65535 / NewBuildingBlockReactant::field_count()))
+				.clamp(1, 65535 / NewBuildingBlockReactant::field_count()))
 			.for_each(|chunk| {
 				let mut conn = db_pool.get().unwrap();
 				db::model::create_building_block_reactants(&mut conn, chunk).unwrap();
 			});
 	});
 
+	let ts_reactants = Utc::now().naive_utc();
+
+	building_block_ids
+		.chunks(65535)
+		.for_each(|chunk| {
+			db::model::update_building_blocks_ts_reactants(&mut conn, chunk, ts_reactants).unwrap();
+		});
+
+	db::model::update_reactions_ts_reactants(&mut conn, &stale_reaction_ids.into_iter().collect_vec(), ts_reactants).unwrap();
+
+	Ok(())
+}
+
+fn status(db_pool: &db::DBPool) -> Result<(), ()> {
+	let mut conn = db_pool.get().unwrap();
+
+	match db::model::get_last_catalog_snapshot(&mut conn) {
+		Ok(snapshot) => {
+			let profile = snapshot.standardizer_profile
+				.as_ref()
+				.and_then(|profile| profile["name"].as_str())
+				.unwrap_or("unknown");
+
+			println!("Last completed catalog snapshot: {} ({}, standardizer profile {profile})", snapshot.id, snapshot.ts);
+
+			if let Some(summary) = &snapshot.import_summary {
+				for (provider, counts) in summary["providers"].as_object().into_iter().flatten() {
+					println!("  {provider}: {} accepted, {} rejected", counts["accepted"], counts["rejected"]);
+				}
+			}
+		},
+		Err(_) => println!("No catalog snapshot."),
+	}
+
+	println!("Compounds:");
+
+	for (provider, counts) in &db::model::count_compounds_by_provider(&mut conn).unwrap().into_iter().group_by(|(provider, _, _)| provider.clone()) {
+		let counts = counts.collect_vec();
+		let count = |available: bool| counts
+			.iter()
+			.find(|(_, counts_available, _)| *counts_available == available)
+			.map_or(0, |(_, _, count)| *count);
+
+		println!("  {provider}: {} available, {} withdrawn", count(true), count(false));
+	}
+
+	let reaction_count = db::model::get_reactions(&mut conn).unwrap().count();
+
+	println!("Reactions: {reaction_count}");
+	println!("Building blocks: {} ({} not matched against the reactions)",
+		db::model::count_building_blocks(&mut conn).unwrap(),
+		db::model::count_building_blocks_pending_reactants(&mut conn).unwrap());
+
 	Ok(())
 }
 
 fn main() {
-	println!("Update started.");
+	let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+		eprintln!("{err}\n\n{}", cli::USAGE);
+		std::process::exit(2);
+	});
+
+	if args.command == Command::Help {
+		println!("{}", cli::USAGE);
+		return;
+	}
+
+	let config = Config::from_env().unwrap();
 
 	let (db_pool, db_thread_pool) = db::pool_with_envfile();
 	let thread_pool = rayon::ThreadPoolBuilder::new()
@@ -762,41 +1386,92 @@ fn main() {
 		.build()
 		.unwrap();
 
-	println!("Bootstrapping...");
+	if args.command == Command::Status {
+		status(&db_pool).unwrap();
+	} else {
+		run(&db_pool, &thread_pool, &config, &args);
+	}
 
-	boostrap(&db_pool).unwrap();
+	drop(db_pool);
 
-	println!("   completed.");
+	while db_thread_pool.strong_count() != 0 {
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+}
 
-	println!("Removing previous compounds...");
+fn run(db_pool: &db::DBPool, thread_pool: &ThreadPool, config: &Config, args: &Args) {
+	let dry_run = match args.dry_run {
+		true => " (dry run)",
+		false => "",
+	};
 
-	remove_prev_compounds(&db_pool).unwrap();
+	println!("Update started{dry_run}.");
 
-	println!(" completed.");
+	if args.command.runs(Command::Bootstrap) {
+		println!("Bootstrapping...");
 
-	println!("Importing new compounds...");
+		bootstrap(db_pool, config, args.dry_run).unwrap();
 
-	import_new_compounds(&db_pool, &thread_pool).unwrap();
+		println!("   completed{dry_run}.");
+	}
 
-	println!(" completed.");
+	if args.command.runs(Command::Import) {
+		let snapshot = match args.dry_run {
+			true => None,
+			false => {
+				println!("Recording catalog snapshot...");
 
-	println!("Computing building block reactants...");
+				let snapshot = record_catalog_snapshot(db_pool, config).unwrap_or_else(|err| {
+					eprintln!("{err}");
+					std::process::exit(1);
+				});
 
-	compute_building_block_reactants(&db_pool, &thread_pool).unwrap();
+				println!(" completed (snapshot {}, standardizer profile {}).", snapshot.id, config.standardizer_profile().unwrap().name);
 
-	println!(" completed.");
+				Some(snapshot)
+			},
+		};
 
-	println!("Optimizing database...");
+		if args.full {
+			println!("Removing previous compounds...");
 
-	db::vacuum_full_analyze(&db_pool).unwrap();
+			if let Some(snapshot) = &snapshot {
+				remove_prev_compounds(db_pool, snapshot).unwrap();
+			}
 
-	println!(" completed.");
+			println!(" completed{dry_run}.");
+		}
 
-	drop(db_pool);
+		println!("Importing new compounds...");
 
-	while db_thread_pool.strong_count() != 0 {
-		std::thread::sleep(std::time::Duration::from_millis(1));
+		import_new_compounds(db_pool, thread_pool, config, snapshot.as_ref()).unwrap();
+
+		// Experiments only run against the snapshot once its catalog is complete
+		if let Some(snapshot) = &snapshot {
+			let mut conn = db_pool.get().unwrap();
+			db::model::update_catalog_snapshot_ts_completed(&mut conn, snapshot.id, Utc::now().naive_utc()).unwrap();
+		}
+
+		println!(" completed{dry_run}.");
+	}
+
+	if args.command.runs(Command::Reactants) {
+		println!("Computing building block reactants...");
+
+		compute_building_block_reactants(db_pool, thread_pool, args.dry_run).unwrap();
+
+		println!(" completed{dry_run}.");
+	}
+
+	if args.command.runs(Command::Vacuum) {
+		println!("Optimizing database...");
+
+		if !args.dry_run {
+			db::vacuum_full_analyze(db_pool).unwrap();
+		}
+
+		println!(" completed{dry_run}.");
 	}
 
-	println!("Update completed successfully.");
+	println!("Update completed successfully{dry_run}.");
 }
@@ -0,0 +1,103 @@
+use crate::config::OfferFields;
+
+/// Commercial details of a vendor compound, as found in the catalog.
+#[derive(Clone, Debug, Default)]
+pub struct CompoundOffer {
+	pub price_per_mg: Option<f32>,
+	/// Comma separated, as written by the vendor (e.g. "1mg,5mg,10mg")
+	pub pack_sizes: Option<String>,
+	pub in_stock: Option<bool>,
+	pub lead_time_days: Option<i32>,
+}
+
+// Numbers of a value, e.g. [2, 3] for "2-3 weeks"
+fn parse_numbers(value: &str) -> Vec<f32> {
+	value
+		.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+		.filter_map(|number| number.parse().ok())
+		.collect()
+}
+
+// Quantity in mg of a pack size such as "5mg", "1 g" or "mg" alone
+fn parse_quantity_mg(value: &str) -> Option<f32> {
+	let value = value.trim();
+	let idx_unit = value.find(|c: char| !(c.is_ascii_digit() || c == '.' || c.is_whitespace())).unwrap_or(value.len());
+	let (amount, unit) = value.split_at(idx_unit);
+
+	let amount = match amount.trim() {
+		"" => 1.0,
+		amount => amount.parse().ok()?,
+	};
+	let mg_per_unit = match unit.trim() {
+		"mg" => 1.0,
+		"g" => 1000.0,
+		"kg" => 1000000.0,
+		"ug" | "µg" | "mcg" => 0.001,
+		_ => return None,
+	};
+
+	(amount > 0.0).then_some(amount * mg_per_unit)
+}
+
+// Prices given for a pack (e.g. "$120/5mg", "95 EUR per 1 g") are divided by its quantity, a bare number is already per mg
+fn parse_price(value: &str) -> Option<f32> {
+	let price = parse_numbers(value).first().copied()?;
+
+	let value = value.to_ascii_lowercase();
+	let pack = ["/", "@", " per ", " for "]
+		.into_iter()
+		.find_map(|sep| value.split_once(sep))
+		.map(|(_, pack)| pack);
+
+	match pack {
+		Some(pack) => parse_quantity_mg(pack).map(|pack_mg| price / pack_mg),
+		None => Some(price),
+	}
+}
+
+fn parse_stock(value: &str) -> Option<bool> {
+	match value.to_ascii_lowercase().as_str() {
+		"y" | "yes" | "true" | "in stock" | "available" => Some(true),
+		"n" | "no" | "false" | "out of stock" | "unavailable" => Some(false),
+		// Stock quantities
+		value => parse_numbers(value).first().map(|quantity| *quantity > 0.0),
+	}
+}
+
+// Ranges are read as their upper bound, so that limits are not exceeded
+fn parse_lead_time_days(value: &str) -> Option<i32> {
+	let days = parse_numbers(value).into_iter().reduce(f32::max)?;
+	let days = match value.to_ascii_lowercase().contains("week") {
+		true => days * 7.0,
+		false => days,
+	};
+
+	Some(days.ceil() as i32)
+}
+
+fn parse_pack_sizes(value: &str) -> Option<String> {
+	let pack_sizes = value
+		.split([',', ';', '|'])
+		.map(str::trim)
+		.filter(|pack_size| !pack_size.is_empty())
+		.collect::<Vec<_>>();
+
+	(!pack_sizes.is_empty()).then(|| pack_sizes.join(","))
+}
+
+impl CompoundOffer {
+	pub fn from_props(fields: &OfferFields, get_prop: impl Fn(&str) -> Option<String>) -> Self {
+		let get = |field: &Option<String>| field
+			.as_deref()
+			.and_then(&get_prop)
+			.map(|value| value.trim().to_owned())
+			.filter(|value| !value.is_empty());
+
+		Self {
+			price_per_mg: get(&fields.price_per_mg).as_deref().and_then(parse_price),
+			pack_sizes: get(&fields.pack_sizes).as_deref().and_then(parse_pack_sizes),
+			in_stock: get(&fields.stock).as_deref().and_then(parse_stock),
+			lead_time_days: get(&fields.lead_time_days).as_deref().and_then(parse_lead_time_days),
+		}
+	}
+}
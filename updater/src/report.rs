@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Step of the import at which a compound was rejected.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStage {
+	Parse,
+	Identifier,
+	Standardize,
+	Smiles,
+	DuplicateId,
+	BuildingBlock,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRejection {
+	pub provider: String,
+	pub refid: Option<String>,
+	/// SMILES of the vendor record before standardization, up to the `Smiles` stage (none for an unparsable SD record).
+	/// The `DuplicateId` and `BuildingBlock` stages work on standardized compounds and store their standardized SMILES,
+	/// space separated for the conflicting entries of an identifier.
+	pub smiles: Option<String>,
+	pub stage: ImportStage,
+	/// Stable identifier of the cause (e.g. the `StandardizeError` variant)
+	pub reason: String,
+	pub message: String,
+}
+
+impl std::fmt::Display for ImportRejection {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Compound {}.{}: {}", self.provider, self.refid.as_deref().unwrap_or("<unknown>"), self.message)
+	}
+}
+
+#[derive(Default)]
+struct ImportCounts {
+	accepted: BTreeMap<String, usize>,
+	rejected: BTreeMap<(String, ImportStage, String), usize>,
+}
+
+/// Rejected compounds of an import, one JSON object per line, along with counts per provider.
+pub struct ImportReport {
+	writer: Mutex<BufWriter<File>>,
+	counts: Mutex<ImportCounts>,
+}
+
+impl ImportReport {
+	pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+		Ok(Self {
+			writer: Mutex::new(BufWriter::new(File::create(path)?)),
+			counts: Mutex::default(),
+		})
+	}
+
+	pub fn accept(&self, provider: &str) {
+		*self.counts.lock().unwrap().accepted.entry(provider.to_owned()).or_default() += 1;
+	}
+
+	pub fn reject(&self, rejection: ImportRejection) {
+		eprintln!("{rejection}");
+
+		{
+			let mut writer = self.writer.lock().unwrap();
+			serde_json::to_writer(&mut *writer, &rejection).unwrap();
+			writer.write_all(b"\n").unwrap();
+		}
+
+		*self.counts.lock().unwrap().rejected
+			.entry((rejection.provider, rejection.stage, rejection.reason))
+			.or_default() += 1;
+	}
+
+	/// Flushes the report and returns the counts per provider and reason.
+	pub fn finish(self) -> io::Result<Value> {
+		self.writer.into_inner().unwrap().flush()?;
+
+		let counts = self.counts.into_inner().unwrap();
+
+		let mut providers = serde_json::Map::new();
+
+		for (provider, accepted) in &counts.accepted {
+			providers.insert(provider.clone(), json!({
+				"accepted": accepted,
+				"rejected": 0,
+				"reasons": [],
+			}));
+		}
+
+		for ((provider, stage, reason), count) in counts.rejected {
+			let entry = providers
+				.entry(provider)
+				.or_insert_with(|| json!({
+					"accepted": 0,
+					"rejected": 0,
+					"reasons": [],
+				}));
+
+			entry["rejected"] = json!(entry["rejected"].as_u64().unwrap() + count as u64);
+			entry["reasons"].as_array_mut().unwrap().push(json!({
+				"stage": stage,
+				"reason": reason,
+				"count": count,
+			}));
+		}
+
+		Ok(json!({
+			"providers": providers,
+		}))
+	}
+}